@@ -1,5 +1,6 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.0.0-rc.5
 
+use super::sea_orm_active_enums::{MatchMode, RuleAction};
 use sea_orm::entity::prelude::*;
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
@@ -10,6 +11,16 @@ pub struct Model {
     pub word: String,
     pub is_regex: bool,
     pub channel_id: Option<i32>,
+    pub normalization: Json,
+    pub match_mode: MatchMode,
+    pub severity: i32,
+    pub action: Option<RuleAction>,
+    pub timeout_duration: Option<i32>,
+    pub author: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub note: Option<String>,
+    pub expires_at: Option<DateTimeWithTimeZone>,
+    pub canary: bool,
     #[sea_orm(created_at)]
     pub created_at: DateTimeWithTimeZone,
     #[sea_orm(updated_at)]
@@ -18,6 +29,8 @@ pub struct Model {
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::blocked_term_links::Entity")]
+    BlockedTermLinks,
     #[sea_orm(
         belongs_to = "super::channels::Entity",
         from = "Column::ChannelId",
@@ -26,6 +39,14 @@ pub enum Relation {
         on_delete = "Cascade"
     )]
     Channels,
+    #[sea_orm(has_many = "super::moderation_actions::Entity")]
+    ModerationActions,
+}
+
+impl Related<super::blocked_term_links::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::BlockedTermLinks.def()
+    }
 }
 
 impl Related<super::channels::Entity> for Entity {
@@ -34,4 +55,10 @@ impl Related<super::channels::Entity> for Entity {
     }
 }
 
+impl Related<super::moderation_actions::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ModerationActions.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
@@ -18,10 +18,22 @@ pub struct Model {
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::banned_username_patterns::Entity")]
+    BannedUsernamePatterns,
     #[sea_orm(has_many = "super::banned_words::Entity")]
     BannedWords,
+    #[sea_orm(has_many = "super::blocked_term_links::Entity")]
+    BlockedTermLinks,
     #[sea_orm(has_many = "super::channel_users::Entity")]
     ChannelUsers,
+    #[sea_orm(has_many = "super::trusted_users::Entity")]
+    TrustedUsers,
+}
+
+impl Related<super::banned_username_patterns::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::BannedUsernamePatterns.def()
+    }
 }
 
 impl Related<super::banned_words::Entity> for Entity {
@@ -30,10 +42,22 @@ impl Related<super::banned_words::Entity> for Entity {
     }
 }
 
+impl Related<super::blocked_term_links::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::BlockedTermLinks.def()
+    }
+}
+
 impl Related<super::channel_users::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::ChannelUsers.def()
     }
 }
 
+impl Related<super::trusted_users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TrustedUsers.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
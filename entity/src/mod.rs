@@ -2,8 +2,14 @@
 
 pub mod prelude;
 
+pub mod banned_username_patterns;
 pub mod banned_words;
+pub mod blocked_term_links;
 pub mod channel_users;
 pub mod channels;
+pub mod enforcement_queue;
+pub mod moderation_actions;
+pub mod sea_orm_active_enums;
+pub mod trusted_users;
 pub mod urls;
 pub mod users;
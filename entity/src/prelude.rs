@@ -1,7 +1,12 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.0.0-rc.5
 
+pub use super::banned_username_patterns::Entity as BannedUsernamePatterns;
 pub use super::banned_words::Entity as BannedWords;
+pub use super::blocked_term_links::Entity as BlockedTermLinks;
 pub use super::channel_users::Entity as ChannelUsers;
 pub use super::channels::Entity as Channels;
+pub use super::enforcement_queue::Entity as EnforcementQueue;
+pub use super::moderation_actions::Entity as ModerationActions;
+pub use super::trusted_users::Entity as TrustedUsers;
 pub use super::urls::Entity as Urls;
 pub use super::users::Entity as Users;
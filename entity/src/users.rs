@@ -10,6 +10,8 @@ pub struct Model {
     #[sea_orm(unique)]
     pub username: String,
     pub is_bot: bool,
+    pub bot_source: Option<String>,
+    pub bot_listed_at: Option<DateTimeWithTimeZone>,
     pub last_seen_at: Option<DateTimeWithTimeZone>,
     #[sea_orm(created_at)]
     pub created_at: DateTimeWithTimeZone,
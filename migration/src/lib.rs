@@ -4,6 +4,18 @@ mod m20220101_000002_create_banned_words_table;
 mod m20241110_180847_create_users_table;
 mod m20241111_195113_create_channel_users_table;
 mod m20241111_195118_create_urls_table;
+mod m20261018_100000_create_trusted_users_table;
+mod m20261018_110000_add_normalization_to_banned_words;
+mod m20261018_120000_add_metadata_to_banned_words;
+mod m20261018_130000_create_moderation_actions_table;
+mod m20261019_090000_add_trace_to_moderation_actions;
+mod m20261019_100000_add_shadow_mode;
+mod m20261019_110000_add_reversal_to_moderation_actions;
+mod m20261019_120000_create_enforcement_queue_table;
+mod m20261019_130000_create_banned_username_patterns_table;
+mod m20261019_140000_add_bot_source_to_users;
+mod m20261019_150000_create_blocked_term_links_table;
+mod m20261019_160000_add_nuke_id_to_moderation_actions;
 
 pub struct Migrator;
 
@@ -16,6 +28,18 @@ impl MigratorTrait for Migrator {
             Box::new(m20241110_180847_create_users_table::Migration),
             Box::new(m20241111_195113_create_channel_users_table::Migration),
             Box::new(m20241111_195118_create_urls_table::Migration),
+            Box::new(m20261018_100000_create_trusted_users_table::Migration),
+            Box::new(m20261018_110000_add_normalization_to_banned_words::Migration),
+            Box::new(m20261018_120000_add_metadata_to_banned_words::Migration),
+            Box::new(m20261018_130000_create_moderation_actions_table::Migration),
+            Box::new(m20261019_090000_add_trace_to_moderation_actions::Migration),
+            Box::new(m20261019_100000_add_shadow_mode::Migration),
+            Box::new(m20261019_110000_add_reversal_to_moderation_actions::Migration),
+            Box::new(m20261019_120000_create_enforcement_queue_table::Migration),
+            Box::new(m20261019_130000_create_banned_username_patterns_table::Migration),
+            Box::new(m20261019_140000_add_bot_source_to_users::Migration),
+            Box::new(m20261019_150000_create_blocked_term_links_table::Migration),
+            Box::new(m20261019_160000_add_nuke_id_to_moderation_actions::Migration),
         ]
     }
 }
@@ -29,6 +29,7 @@ impl MigrationTrait for Migration {
 }
 
 #[derive(DeriveIden)]
+#[allow(clippy::enum_variant_names)]
 enum Url {
     #[sea_orm(iden = "urls")]
     Table,
@@ -0,0 +1,68 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(TrustedUser::Table)
+                    .if_not_exists()
+                    .col(pk_auto(TrustedUser::Id))
+                    .col(integer(TrustedUser::ChannelId).not_null())
+                    .col(string(TrustedUser::Username).not_null())
+                    .col(string_null(TrustedUser::AddedBy))
+                    .col(timestamp_with_time_zone(TrustedUser::CreatedAt).not_null().default(Expr::current_timestamp()))
+                    .col(timestamp_with_time_zone(TrustedUser::UpdatedAt).not_null().default(Expr::current_timestamp()))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager.create_index(
+            Index::create()
+                .name("idx_trusted_users_channel_username")
+                .table(TrustedUser::Table)
+                .col(TrustedUser::ChannelId)
+                .col(TrustedUser::Username)
+                .unique()
+                .to_owned()
+        ).await?;
+
+        manager.create_foreign_key(
+            ForeignKey::create()
+                .from(TrustedUser::Table, TrustedUser::ChannelId)
+                .to(Channel::Table, Channel::Id)
+                .on_delete(ForeignKeyAction::Cascade)
+                .on_update(ForeignKeyAction::Cascade)
+                .to_owned()
+        ).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TrustedUser::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum TrustedUser {
+    #[sea_orm(iden = "trusted_users")]
+    Table,
+    Id,
+    ChannelId,
+    Username,
+    AddedBy,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Channel {
+    #[sea_orm(iden = "channels")]
+    Table,
+    Id,
+}
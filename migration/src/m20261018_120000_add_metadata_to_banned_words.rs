@@ -0,0 +1,63 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(BannedWord::Table)
+                    .add_column(string(BannedWord::MatchMode).not_null().default("substring"))
+                    .add_column(integer(BannedWord::Severity).not_null().default(1))
+                    .add_column(string_null(BannedWord::Action))
+                    .add_column(integer_null(BannedWord::TimeoutDuration))
+                    .add_column(string_null(BannedWord::Author))
+                    .add_column(text_null(BannedWord::Note))
+                    .add_column(timestamp_with_time_zone_null(BannedWord::ExpiresAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        let update = Query::update()
+            .table(BannedWord::Table)
+            .value(BannedWord::MatchMode, "regex")
+            .and_where(Expr::col(BannedWord::IsRegex).eq(true))
+            .to_owned();
+
+        manager.exec_stmt(update).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(BannedWord::Table)
+                    .drop_column(BannedWord::MatchMode)
+                    .drop_column(BannedWord::Severity)
+                    .drop_column(BannedWord::Action)
+                    .drop_column(BannedWord::TimeoutDuration)
+                    .drop_column(BannedWord::Author)
+                    .drop_column(BannedWord::Note)
+                    .drop_column(BannedWord::ExpiresAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum BannedWord {
+    #[sea_orm(iden = "banned_words")]
+    Table,
+    IsRegex,
+    MatchMode,
+    Severity,
+    Action,
+    TimeoutDuration,
+    Author,
+    Note,
+    ExpiresAt,
+}
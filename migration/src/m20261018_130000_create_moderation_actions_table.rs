@@ -0,0 +1,81 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ModerationAction::Table)
+                    .if_not_exists()
+                    .col(pk_auto(ModerationAction::Id))
+                    .col(string(ModerationAction::Channel).not_null())
+                    .col(string(ModerationAction::TargetUserId).not_null())
+                    .col(string(ModerationAction::TargetLogin).not_null())
+                    .col(string(ModerationAction::Action).not_null())
+                    .col(integer_null(ModerationAction::Duration))
+                    .col(integer_null(ModerationAction::RuleId))
+                    .col(text_null(ModerationAction::MessageText))
+                    .col(string(ModerationAction::Actor).not_null())
+                    .col(boolean(ModerationAction::Succeeded).not_null())
+                    .col(text_null(ModerationAction::Error))
+                    .col(timestamp_with_time_zone(ModerationAction::CreatedAt).not_null().default(Expr::current_timestamp()))
+                    .col(timestamp_with_time_zone(ModerationAction::UpdatedAt).not_null().default(Expr::current_timestamp()))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager.create_index(
+            Index::create()
+                .name("idx_moderation_actions_channel_target_login")
+                .table(ModerationAction::Table)
+                .col(ModerationAction::Channel)
+                .col(ModerationAction::TargetLogin)
+                .to_owned()
+        ).await?;
+
+        manager.create_foreign_key(
+            ForeignKey::create()
+                .from(ModerationAction::Table, ModerationAction::RuleId)
+                .to(BannedWord::Table, BannedWord::Id)
+                .on_delete(ForeignKeyAction::SetNull)
+                .on_update(ForeignKeyAction::Cascade)
+                .to_owned()
+        ).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ModerationAction::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ModerationAction {
+    #[sea_orm(iden = "moderation_actions")]
+    Table,
+    Id,
+    Channel,
+    TargetUserId,
+    TargetLogin,
+    Action,
+    Duration,
+    RuleId,
+    MessageText,
+    Actor,
+    Succeeded,
+    Error,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum BannedWord {
+    #[sea_orm(iden = "banned_words")]
+    Table,
+    Id,
+}
@@ -0,0 +1,61 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(BannedWord::Table)
+                    .add_column(boolean(BannedWord::Canary).not_null().default(false))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ModerationAction::Table)
+                    .add_column(boolean(ModerationAction::Shadow).not_null().default(false))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ModerationAction::Table)
+                    .drop_column(ModerationAction::Shadow)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(BannedWord::Table)
+                    .drop_column(BannedWord::Canary)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum BannedWord {
+    #[sea_orm(iden = "banned_words")]
+    Table,
+    Canary,
+}
+
+#[derive(DeriveIden)]
+enum ModerationAction {
+    #[sea_orm(iden = "moderation_actions")]
+    Table,
+    Shadow,
+}
@@ -0,0 +1,39 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ModerationAction::Table)
+                    .add_column(timestamp_with_time_zone_null(ModerationAction::ReversedAt))
+                    .add_column(string_null(ModerationAction::ReversedBy))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ModerationAction::Table)
+                    .drop_column(ModerationAction::ReversedAt)
+                    .drop_column(ModerationAction::ReversedBy)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ModerationAction {
+    #[sea_orm(iden = "moderation_actions")]
+    Table,
+    ReversedAt,
+    ReversedBy,
+}
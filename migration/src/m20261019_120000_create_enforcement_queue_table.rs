@@ -0,0 +1,87 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(EnforcementQueue::Table)
+                    .if_not_exists()
+                    .col(pk_auto(EnforcementQueue::Id))
+                    .col(integer_null(EnforcementQueue::ModerationActionId))
+                    .col(string(EnforcementQueue::Channel).not_null())
+                    .col(string(EnforcementQueue::BroadcasterId).not_null())
+                    .col(string(EnforcementQueue::TargetUserId).not_null())
+                    .col(string(EnforcementQueue::TargetLogin).not_null())
+                    .col(string(EnforcementQueue::Action).not_null())
+                    .col(integer_null(EnforcementQueue::Duration))
+                    .col(string_null(EnforcementQueue::MessageId))
+                    .col(string(EnforcementQueue::Reason).not_null())
+                    .col(string(EnforcementQueue::Status).not_null().default("pending"))
+                    .col(integer(EnforcementQueue::Attempts).not_null().default(0))
+                    .col(timestamp_with_time_zone(EnforcementQueue::NextAttemptAt).not_null())
+                    .col(text_null(EnforcementQueue::LastError))
+                    .col(timestamp_with_time_zone(EnforcementQueue::CreatedAt).not_null().default(Expr::current_timestamp()))
+                    .col(timestamp_with_time_zone(EnforcementQueue::UpdatedAt).not_null().default(Expr::current_timestamp()))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager.create_index(
+            Index::create()
+                .name("idx_enforcement_queue_status_next_attempt_at")
+                .table(EnforcementQueue::Table)
+                .col(EnforcementQueue::Status)
+                .col(EnforcementQueue::NextAttemptAt)
+                .to_owned()
+        ).await?;
+
+        manager.create_foreign_key(
+            ForeignKey::create()
+                .from(EnforcementQueue::Table, EnforcementQueue::ModerationActionId)
+                .to(ModerationAction::Table, ModerationAction::Id)
+                .on_delete(ForeignKeyAction::SetNull)
+                .on_update(ForeignKeyAction::Cascade)
+                .to_owned()
+        ).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(EnforcementQueue::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum EnforcementQueue {
+    #[sea_orm(iden = "enforcement_queue")]
+    Table,
+    Id,
+    ModerationActionId,
+    Channel,
+    BroadcasterId,
+    TargetUserId,
+    TargetLogin,
+    Action,
+    Duration,
+    MessageId,
+    Reason,
+    Status,
+    Attempts,
+    NextAttemptAt,
+    LastError,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum ModerationAction {
+    #[sea_orm(iden = "moderation_actions")]
+    Table,
+    Id,
+}
@@ -0,0 +1,68 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(BannedUsernamePattern::Table)
+                    .if_not_exists()
+                    .col(pk_auto(BannedUsernamePattern::Id))
+                    .col(integer_null(BannedUsernamePattern::ChannelId))
+                    .col(string(BannedUsernamePattern::Pattern).not_null())
+                    .col(string(BannedUsernamePattern::Kind).not_null().default("regex"))
+                    .col(double_null(BannedUsernamePattern::Similarity))
+                    .col(string_null(BannedUsernamePattern::Action))
+                    .col(integer_null(BannedUsernamePattern::TimeoutDuration))
+                    .col(string_null(BannedUsernamePattern::Author))
+                    .col(text_null(BannedUsernamePattern::Note))
+                    .col(timestamp_with_time_zone(BannedUsernamePattern::CreatedAt).not_null().default(Expr::current_timestamp()))
+                    .col(timestamp_with_time_zone(BannedUsernamePattern::UpdatedAt).not_null().default(Expr::current_timestamp()))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager.create_foreign_key(
+            ForeignKey::create()
+                .from(BannedUsernamePattern::Table, BannedUsernamePattern::ChannelId)
+                .to(Channel::Table, Channel::Id)
+                .on_delete(ForeignKeyAction::Cascade)
+                .on_update(ForeignKeyAction::Cascade)
+                .to_owned()
+        ).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(BannedUsernamePattern::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum BannedUsernamePattern {
+    #[sea_orm(iden = "banned_username_patterns")]
+    Table,
+    Id,
+    ChannelId,
+    Pattern,
+    Kind,
+    Similarity,
+    Action,
+    TimeoutDuration,
+    Author,
+    Note,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Channel {
+    #[sea_orm(iden = "channels")]
+    Table,
+    Id,
+}
@@ -0,0 +1,88 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(BlockedTermLink::Table)
+                    .if_not_exists()
+                    .col(pk_auto(BlockedTermLink::Id))
+                    .col(integer(BlockedTermLink::ChannelId).not_null())
+                    .col(string(BlockedTermLink::TermId).not_null())
+                    .col(string(BlockedTermLink::TermText).not_null())
+                    .col(integer_null(BlockedTermLink::BannedWordId))
+                    .col(string(BlockedTermLink::Word).not_null())
+                    .col(timestamp_with_time_zone(BlockedTermLink::CreatedAt).not_null().default(Expr::current_timestamp()))
+                    .col(timestamp_with_time_zone(BlockedTermLink::UpdatedAt).not_null().default(Expr::current_timestamp()))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager.create_index(
+            Index::create()
+                .name("idx_blocked_term_links_channel_term")
+                .table(BlockedTermLink::Table)
+                .col(BlockedTermLink::ChannelId)
+                .col(BlockedTermLink::TermId)
+                .unique()
+                .to_owned()
+        ).await?;
+
+        manager.create_foreign_key(
+            ForeignKey::create()
+                .from(BlockedTermLink::Table, BlockedTermLink::ChannelId)
+                .to(Channel::Table, Channel::Id)
+                .on_delete(ForeignKeyAction::Cascade)
+                .on_update(ForeignKeyAction::Cascade)
+                .to_owned()
+        ).await?;
+
+        manager.create_foreign_key(
+            ForeignKey::create()
+                .from(BlockedTermLink::Table, BlockedTermLink::BannedWordId)
+                .to(BannedWord::Table, BannedWord::Id)
+                .on_delete(ForeignKeyAction::SetNull)
+                .on_update(ForeignKeyAction::Cascade)
+                .to_owned()
+        ).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(BlockedTermLink::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum BlockedTermLink {
+    #[sea_orm(iden = "blocked_term_links")]
+    Table,
+    Id,
+    ChannelId,
+    TermId,
+    TermText,
+    BannedWordId,
+    Word,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Channel {
+    #[sea_orm(iden = "channels")]
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum BannedWord {
+    #[sea_orm(iden = "banned_words")]
+    Table,
+    Id,
+}
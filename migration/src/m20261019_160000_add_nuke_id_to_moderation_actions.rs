@@ -0,0 +1,52 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ModerationAction::Table)
+                    .add_column(string_null(ModerationAction::NukeId))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Nukes recorded before the column only carry their ID in the trace
+        manager
+            .get_connection()
+            .execute_unprepared("UPDATE moderation_actions SET nuke_id = trace->>'nuke' WHERE trace->>'nuke' IS NOT NULL")
+            .await?;
+
+        manager.create_index(
+            Index::create()
+                .name("idx_moderation_actions_channel_nuke_id")
+                .table(ModerationAction::Table)
+                .col(ModerationAction::Channel)
+                .col(ModerationAction::NukeId)
+                .to_owned()
+        ).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ModerationAction::Table)
+                    .drop_column(ModerationAction::NukeId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ModerationAction {
+    #[sea_orm(iden = "moderation_actions")]
+    Table,
+    Channel,
+    NukeId,
+}
@@ -0,0 +1,411 @@
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use entity::moderation_actions;
+use entity::sea_orm_active_enums::MatchMode;
+use sea_orm::ActiveEnum;
+use serde::Deserialize;
+use serde_json::Value;
+use subtle::ConstantTimeEq;
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc::Sender, oneshot};
+use tracing::{error, info};
+use crate::bot::BotEvent;
+use crate::moderation::RulePrecision;
+
+/// Shortest `ADMIN_TOKEN` the admin API starts with
+pub const MIN_TOKEN_LENGTH: usize = 16;
+
+/**
+ * Requests from the admin API, answered by the bot's main loop
+ */
+pub enum AdminRequest {
+    Why {
+        channel: String,
+        user: String,
+        reply: oneshot::Sender<Result<Option<moderation_actions::Model>, String>>,
+    },
+    Unban {
+        channel: String,
+        user: String,
+        reply: oneshot::Sender<Result<Option<moderation_actions::Model>, String>>,
+    },
+    Precision {
+        reply: oneshot::Sender<Result<Vec<RulePrecision>, String>>,
+    },
+    /// Add a banned word, global without a channel. Rejected words are answered with `Ok(Err(..))`.
+    AddBannedWord {
+        word: String,
+        mode: MatchMode,
+        channel: Option<String>,
+        reply: oneshot::Sender<Result<Result<i32, String>, String>>,
+    },
+    /// Lift a circuit breaker trip across all channels, answered with whether it had tripped
+    Resume {
+        reply: oneshot::Sender<Result<bool, String>>,
+    },
+}
+
+#[derive(Clone)]
+struct AdminState {
+    token: String,
+    events: Sender<BotEvent>,
+}
+
+/**
+ * Serve the admin API until the listener fails
+ */
+pub async fn serve(listener: TcpListener, token: String, events: Sender<BotEvent>) -> std::io::Result<()> {
+    info!("Admin API listening on {}", listener.local_addr()?);
+
+    let app = Router::new()
+        .route("/channels/{channel}/users/{user}/why", get(why))
+        .route("/channels/{channel}/users/{user}/unban", post(unban))
+        .route("/rules/precision", get(precision))
+        .route("/banned-words", post(add_banned_word))
+        .route("/circuit-breaker/resume", post(resume))
+        .with_state(AdminState { token, events });
+
+    axum::serve(listener, app).await
+}
+
+fn authorize(state: &AdminState, headers: &HeaderMap) -> Result<(), StatusCode> {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    // Compared in constant time so that the token cannot be guessed byte by byte
+    match token {
+        Some(token) if bool::from(token.as_bytes().ct_eq(state.token.as_bytes())) => Ok(()),
+        _ => Err(StatusCode::UNAUTHORIZED),
+    }
+}
+
+/**
+ * Send a request to the bot and wait for its answer
+ */
+async fn ask<T>(state: &AdminState, request: impl FnOnce(oneshot::Sender<Result<T, String>>) -> AdminRequest) -> Result<T, StatusCode> {
+    let (reply, response) = oneshot::channel();
+    state.events.send(BotEvent::Admin(request(reply))).await.map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+
+    match response.await {
+        Ok(Ok(value)) => Ok(value),
+        Ok(Err(e)) => {
+            error!("Admin request failed: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        },
+        Err(_) => Err(StatusCode::SERVICE_UNAVAILABLE),
+    }
+}
+
+/**
+ * Latest moderation decision against a user, with its trace
+ */
+async fn why(
+    State(state): State<AdminState>,
+    headers: HeaderMap,
+    Path((channel, user)): Path<(String, String)>,
+) -> Result<Json<Value>, StatusCode> {
+    authorize(&state, &headers)?;
+
+    let action = ask(&state, |reply| AdminRequest::Why {
+        channel: channel.to_lowercase(),
+        user: user.to_lowercase(),
+        reply,
+    }).await?;
+
+    action.map(|action| Json(action_json(&action))).ok_or(StatusCode::NOT_FOUND)
+}
+
+/**
+ * Undo the bot's latest ban or timeout against a user, the reversed audit entry is returned
+ */
+async fn unban(
+    State(state): State<AdminState>,
+    headers: HeaderMap,
+    Path((channel, user)): Path<(String, String)>,
+) -> Result<Json<Value>, StatusCode> {
+    authorize(&state, &headers)?;
+
+    let action = ask(&state, |reply| AdminRequest::Unban {
+        channel: channel.to_lowercase(),
+        user: user.to_lowercase(),
+        reply,
+    }).await?;
+
+    action.map(|action| Json(action_json(&action))).ok_or(StatusCode::NOT_FOUND)
+}
+
+/**
+ * Precision of every rule that has enforced actions
+ */
+async fn precision(State(state): State<AdminState>, headers: HeaderMap) -> Result<Json<Value>, StatusCode> {
+    authorize(&state, &headers)?;
+
+    let rules = ask(&state, |reply| AdminRequest::Precision { reply }).await?;
+    let rules: Vec<Value> = rules.iter().map(|rule| serde_json::json!({
+        "rule_id": rule.rule_id,
+        "actions": rule.actions,
+        "false_positives": rule.false_positives,
+        "precision": rule.precision(),
+    })).collect();
+
+    Ok(Json(Value::Array(rules)))
+}
+
+/**
+ * Banned word to add, in every channel unless one is given
+ */
+#[derive(Debug, Deserialize)]
+struct NewBannedWord {
+    word: String,
+    match_mode: Option<String>,
+    channel: Option<String>,
+}
+
+/**
+ * Add a banned word, the only way to add one for every channel
+ */
+async fn add_banned_word(
+    State(state): State<AdminState>,
+    headers: HeaderMap,
+    Json(body): Json<NewBannedWord>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    authorize(&state, &headers).map_err(|status| (status, Json(Value::Null)))?;
+
+    let mode = match &body.match_mode {
+        Some(mode) => MatchMode::try_from_value(mode).map_err(|_| {
+            (StatusCode::UNPROCESSABLE_ENTITY, Json(serde_json::json!({ "error": format!("unknown match mode {}", mode) })))
+        })?,
+        None => MatchMode::Substring,
+    };
+    let result = ask(&state, |reply| AdminRequest::AddBannedWord {
+        word: body.word,
+        mode,
+        channel: body.channel.map(|channel| channel.to_lowercase()),
+        reply,
+    }).await.map_err(|status| (status, Json(Value::Null)))?;
+
+    match result {
+        Ok(id) => Ok(Json(serde_json::json!({ "id": id }))),
+        Err(e) => Err((StatusCode::UNPROCESSABLE_ENTITY, Json(serde_json::json!({ "error": e })))),
+    }
+}
+
+/**
+ * Resume enforcement after the circuit breaker tripped across all channels
+ */
+async fn resume(State(state): State<AdminState>, headers: HeaderMap) -> Result<Json<Value>, StatusCode> {
+    authorize(&state, &headers)?;
+
+    let resumed = ask(&state, |reply| AdminRequest::Resume { reply }).await?;
+    Ok(Json(serde_json::json!({ "resumed": resumed })))
+}
+
+pub fn action_json(action: &moderation_actions::Model) -> Value {
+    serde_json::json!({
+        "id": action.id,
+        "channel": action.channel,
+        "target_user_id": action.target_user_id,
+        "target_login": action.target_login,
+        "action": action.action,
+        "duration": action.duration,
+        "rule_id": action.rule_id,
+        "message_text": action.message_text,
+        "actor": action.actor,
+        "succeeded": action.succeeded,
+        "error": action.error,
+        "trace": action.trace,
+        "shadow": action.shadow,
+        "reversed_at": action.reversed_at.map(|reversed_at| reversed_at.to_rfc3339()),
+        "reversed_by": action.reversed_by,
+        "nuke_id": action.nuke_id,
+        "created_at": action.created_at.to_rfc3339(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    #[test]
+    fn test_authorize() {
+        let (events, _receiver) = mpsc::channel(1);
+        let state = AdminState { token: "secret".to_string(), events };
+        let headers = |value: &str| HeaderMap::from_iter([(header::AUTHORIZATION, value.parse().unwrap())]);
+
+        assert_eq!(authorize(&state, &headers("Bearer secret")), Ok(()));
+        assert_eq!(authorize(&state, &headers("Bearer secreT")), Err(StatusCode::UNAUTHORIZED));
+        assert_eq!(authorize(&state, &headers("Bearer secret2")), Err(StatusCode::UNAUTHORIZED));
+        assert_eq!(authorize(&state, &headers("Bearer ")), Err(StatusCode::UNAUTHORIZED));
+        assert_eq!(authorize(&state, &HeaderMap::new()), Err(StatusCode::UNAUTHORIZED));
+    }
+
+    /**
+     * Serve the admin API on a free port with the token "secret". Requests go
+     * to `handler`, a stand-in for the bot's main loop. Returns the base URL.
+     */
+    async fn spawn_admin(mut handler: impl FnMut(AdminRequest) + Send + 'static) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (events, mut receiver) = mpsc::channel(10);
+        tokio::spawn(serve(listener, "secret".to_string(), events));
+
+        tokio::spawn(async move {
+            while let Some(BotEvent::Admin(request)) = receiver.recv().await {
+                handler(request);
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    fn test_action(login: String) -> moderation_actions::Model {
+        let now = chrono::Utc::now().fixed_offset();
+        moderation_actions::Model {
+            id: 1,
+            channel: "test_channel".to_string(),
+            target_user_id: "123".to_string(),
+            target_login: login,
+            action: "ban".to_string(),
+            duration: None,
+            rule_id: Some(3),
+            message_text: Some("cheap viewers".to_string()),
+            actor: "test_user".to_string(),
+            succeeded: true,
+            error: None,
+            trace: Some(serde_json::json!({ "decided_by": 3 })),
+            shadow: false,
+            reversed_at: None,
+            reversed_by: None,
+            nuke_id: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_why_endpoint() {
+        let base = spawn_admin(|request| {
+            if let AdminRequest::Why { user, reply, .. } = request {
+                let _ = reply.send(Ok((user == "spammer").then(|| test_action(user))));
+            }
+        }).await;
+
+        let client = reqwest::Client::new();
+        let url = |user: &str| format!("{}/channels/test_channel/users/{}/why", base, user);
+
+        let response = client.get(url("spammer")).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+        let response = client.get(url("Spammer")).bearer_auth("secret").send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let body: Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+        assert_eq!(body["rule_id"], 3);
+        assert_eq!(body["trace"]["decided_by"], 3);
+
+        let response = client.get(url("someone")).bearer_auth("secret").send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_unban_endpoint() {
+        let base = spawn_admin(|request| {
+            if let AdminRequest::Unban { channel, user, reply } = request {
+                let reversed = (channel == "test_channel" && user == "regular").then(|| moderation_actions::Model {
+                    reversed_at: Some(chrono::Utc::now().fixed_offset()),
+                    reversed_by: Some("admin".to_string()),
+                    ..test_action(user)
+                });
+                let _ = reply.send(Ok(reversed));
+            }
+        }).await;
+
+        let client = reqwest::Client::new();
+        let url = |user: &str| format!("{}/channels/Test_Channel/users/{}/unban", base, user);
+
+        let response = client.post(url("regular")).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+        let response = client.post(url("regular")).bearer_auth("wrong-token").send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+        let response = client.post(url("Regular")).bearer_auth("secret").send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let body: Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+        assert_eq!(body["target_login"], "regular");
+        assert_eq!(body["reversed_by"], "admin");
+
+        let response = client.post(url("someone")).bearer_auth("secret").send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_precision_endpoint() {
+        let base = spawn_admin(|request| {
+            if let AdminRequest::Precision { reply } = request {
+                let _ = reply.send(Ok(vec![RulePrecision { rule_id: 3, actions: 4, false_positives: 1 }]));
+            }
+        }).await;
+
+        let response = reqwest::Client::new()
+            .get(format!("{}/rules/precision", base))
+            .bearer_auth("secret")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let body: Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+        assert_eq!(body[0]["rule_id"], 3);
+        assert_eq!(body[0]["precision"], 0.75);
+    }
+
+    #[tokio::test]
+    async fn test_resume_endpoint() {
+        let base = spawn_admin(|request| {
+            if let AdminRequest::Resume { reply } = request {
+                let _ = reply.send(Ok(true));
+            }
+        }).await;
+
+        let client = reqwest::Client::new();
+        let url = format!("{}/circuit-breaker/resume", base);
+        let response = client.post(&url).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+        let response = client.post(&url).bearer_auth("secret").send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let body: Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+        assert_eq!(body["resumed"], true);
+    }
+
+    #[tokio::test]
+    async fn test_add_banned_word_endpoint() {
+        let base = spawn_admin(|request| {
+            if let AdminRequest::AddBannedWord { word, mode, channel, reply } = request {
+                let result = match (word.as_str(), mode, channel) {
+                    ("promo", MatchMode::WholeWord, None) => Ok(7),
+                    _ => Err("pattern matches the empty string".to_string()),
+                };
+                let _ = reply.send(Ok(result));
+            }
+        }).await;
+
+        let client = reqwest::Client::new();
+        let url = format!("{}/banned-words", base);
+        let add = |body: Value| client.post(&url).bearer_auth("secret").header("content-type", "application/json").body(body.to_string()).send();
+
+        let response = add(serde_json::json!({ "word": "promo", "match_mode": "whole_word" })).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let body: Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+        assert_eq!(body["id"], 7);
+
+        let response = add(serde_json::json!({ "word": ".*", "channel": "Test_Channel" })).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNPROCESSABLE_ENTITY);
+        let response = add(serde_json::json!({ "word": "promo", "match_mode": "fuzzy" })).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNPROCESSABLE_ENTITY);
+        let response = client.post(&url).header("content-type", "application/json").body(r#"{"word": "promo"}"#).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+    }
+}
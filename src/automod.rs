@@ -0,0 +1,156 @@
+use std::fmt;
+use crate::eventsub::AutomodHoldEvent;
+use crate::helix::HelixError;
+use crate::settings::AutomodReview;
+
+/// Characters of the held message quoted in notices
+const NOTICE_TEXT_CHARS: usize = 200;
+
+/**
+ * What the bot's own rules and trust data say about a held message
+ */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HoldFacts {
+    /// Enforced banned word rule the message matches
+    pub rule_id: Option<i32>,
+    /// Canary rules only ever shadow, so their matches are not trusted
+    pub canary: bool,
+    pub spam_url: Option<String>,
+    pub known_bot: bool,
+    /// Username pattern the login matches
+    pub username_rule_id: Option<i32>,
+    pub trusted: bool,
+    pub seen: bool,
+}
+
+/**
+ * Outcome of reviewing a held message
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Approve(String),
+    Deny(String),
+    /// Not confident either way, mods have to look at it
+    Escalate,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Verdict::Approve(reason) => write!(f, "approve ({})", reason),
+            Verdict::Deny(reason) => write!(f, "deny ({})", reason),
+            Verdict::Escalate => f.write_str("escalate"),
+        }
+    }
+}
+
+/**
+ * Review a held message. Messages the bot would act on are denied, messages
+ * from trusted or familiar users that match nothing are approved, and
+ * anything with evidence both ways is escalated.
+ */
+pub fn review(settings: &AutomodReview, hold: &AutomodHoldEvent, facts: &HoldFacts) -> Verdict {
+    let deny_reason = if let Some(rule_id) = facts.rule_id.filter(|_| !facts.canary) {
+        Some(format!("matches rule {}", rule_id))
+    } else if let Some(url) = &facts.spam_url {
+        Some(format!("spam URL {}", url))
+    } else if facts.known_bot {
+        Some("known bot".to_string())
+    } else {
+        facts.username_rule_id.map(|rule_id| format!("username pattern #{}", rule_id))
+    };
+    let suspicious = deny_reason.is_some() || facts.rule_id.is_some();
+
+    match deny_reason {
+        Some(_) if facts.trusted => Verdict::Escalate,
+        Some(reason) if settings.deny_matches => Verdict::Deny(reason),
+        Some(_) => Verdict::Escalate,
+        None if suspicious => Verdict::Escalate,
+        None if facts.trusted && settings.approve_trusted => Verdict::Approve("trusted user".to_string()),
+        None if facts.seen && settings.approve_seen_max_level.is_some_and(|max| hold.level <= max) => {
+            Verdict::Approve(format!("regular, level {}", hold.level))
+        },
+        None => Verdict::Escalate,
+    }
+}
+
+/**
+ * Short notice asking mods to review a held message
+ */
+pub fn notice(hold: &AutomodHoldEvent) -> String {
+    let mut text: String = hold.message.text.chars().take(NOTICE_TEXT_CHARS).collect();
+    if text.len() < hold.message.text.len() {
+        text.push('…');
+    }
+    format!(
+        "AutoMod held a message from {} in #{} ({}, level {}), please review: {}",
+        hold.user_login, hold.broadcaster_user_login, hold.category, hold.level, text,
+    )
+}
+
+/**
+ * Post a notice to a webhook, as `content` for Discord and `text` for Slack
+ */
+pub async fn post_notice(http: &reqwest::Client, url: &str, text: &str) -> Result<(), HelixError> {
+    let response = http.post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(serde_json::json!({ "content": text, "text": text }).to_string())
+        .send()
+        .await
+        .map_err(|e| HelixError::unavailable(&e.to_string()))?;
+
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    Err(HelixError {
+        message: response.text().await.unwrap_or_default(),
+        status: Some(status.as_u16()),
+        retry_at: None,
+        permanent: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eventsub::MessageBody;
+
+    fn hold(level: u8, text: &str) -> AutomodHoldEvent {
+        AutomodHoldEvent {
+            broadcaster_user_id: "1".to_string(),
+            broadcaster_user_login: "test_channel".to_string(),
+            user_id: "123".to_string(),
+            user_login: "viewer".to_string(),
+            message_id: "abc".to_string(),
+            message: MessageBody { text: text.to_string() },
+            category: "swearing".to_string(),
+            level,
+        }
+    }
+
+    #[test]
+    fn test_review() {
+        let settings = AutomodReview { enabled: true, approve_seen_max_level: Some(1), ..Default::default() };
+        let review = |level: u8, facts: HoldFacts| review(&settings, &hold(level, "cheap viewers"), &facts);
+
+        assert_eq!(review(2, HoldFacts { rule_id: Some(3), ..Default::default() }), Verdict::Deny("matches rule 3".to_string()));
+        assert_eq!(review(2, HoldFacts { known_bot: true, seen: true, ..Default::default() }), Verdict::Deny("known bot".to_string()));
+        assert_eq!(review(2, HoldFacts { rule_id: Some(3), canary: true, ..Default::default() }), Verdict::Escalate);
+        assert_eq!(review(2, HoldFacts { rule_id: Some(3), trusted: true, ..Default::default() }), Verdict::Escalate);
+        assert_eq!(review(2, HoldFacts { trusted: true, ..Default::default() }), Verdict::Approve("trusted user".to_string()));
+        assert_eq!(review(2, HoldFacts { seen: true, ..Default::default() }), Verdict::Escalate);
+        assert_eq!(review(1, HoldFacts { seen: true, ..Default::default() }), Verdict::Approve("regular, level 1".to_string()));
+        assert_eq!(review(1, HoldFacts::default()), Verdict::Escalate);
+
+        let cautious = AutomodReview { deny_matches: false, ..settings.clone() };
+        let facts = HoldFacts { rule_id: Some(3), ..Default::default() };
+        assert_eq!(super::review(&cautious, &hold(2, "cheap viewers"), &facts), Verdict::Escalate);
+    }
+
+    #[test]
+    fn test_notice() {
+        assert_eq!(notice(&hold(2, "darn it")), "AutoMod held a message from viewer in #test_channel (swearing, level 2), please review: darn it");
+        assert!(notice(&hold(2, &"a".repeat(300))).ends_with(&format!("{}…", "a".repeat(200))));
+    }
+}
@@ -0,0 +1,152 @@
+use twitch_irc::message::{Badge, IRCTags};
+
+/**
+ * Chat badges the bot cares about, parsed from the `badges` tag
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChatBadge {
+    Broadcaster,
+    Moderator,
+    Vip,
+    Subscriber,
+    Founder,
+    Artist,
+    Staff,
+    Other(String),
+}
+
+impl ChatBadge {
+    pub fn from_name(name: &str) -> ChatBadge {
+        match name {
+            "broadcaster" => ChatBadge::Broadcaster,
+            "moderator" => ChatBadge::Moderator,
+            "vip" => ChatBadge::Vip,
+            "subscriber" => ChatBadge::Subscriber,
+            "founder" => ChatBadge::Founder,
+            "artist-badge" => ChatBadge::Artist,
+            "staff" | "admin" | "global_mod" => ChatBadge::Staff,
+            other => ChatBadge::Other(other.to_string()),
+        }
+    }
+}
+
+/**
+ * Badges of a single chatter together with the details from `badge-info`
+ */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UserBadges {
+    badges: Vec<ChatBadge>,
+    subscriber_months: u32,
+}
+
+impl UserBadges {
+    /**
+     * Read the `badges` and `badge-info` tags of an IRC message
+     */
+    pub fn from_tags(tags: &IRCTags) -> UserBadges {
+        let tag = |name: &str| match tags.0.get(name) {
+            Some(Some(value)) => value.as_str(),
+            _ => "",
+        };
+        UserBadges::parse(tag("badges"), tag("badge-info"))
+    }
+
+    /**
+     * Parse raw `badges` and `badge-info` tag values, e.g. `broadcaster/1,subscriber/12`
+     */
+    pub fn parse(badges: &str, badge_info: &str) -> UserBadges {
+        UserBadges::from_badges(&parse_badge_list(badges), &parse_badge_list(badge_info))
+    }
+
+    fn from_badges(badges: &[Badge], badge_info: &[Badge]) -> UserBadges {
+        let subscriber_months = badge_info
+            .iter()
+            .find(|info| info.name == "subscriber" || info.name == "founder")
+            .and_then(|info| info.version.parse().ok())
+            .unwrap_or(0);
+
+        UserBadges {
+            badges: badges.iter().map(|badge| ChatBadge::from_name(&badge.name)).collect(),
+            subscriber_months,
+        }
+    }
+
+    pub fn has(&self, badge: &ChatBadge) -> bool {
+        self.badges.contains(badge)
+    }
+
+    pub fn is_broadcaster(&self) -> bool {
+        self.has(&ChatBadge::Broadcaster)
+    }
+
+    pub fn is_mod(&self) -> bool {
+        self.has(&ChatBadge::Moderator)
+    }
+
+    pub fn is_vip(&self) -> bool {
+        self.has(&ChatBadge::Vip)
+    }
+
+    /**
+     * Number of months subscribed, 0 when the user is not a subscriber
+     */
+    pub fn subscriber_months(&self) -> u32 {
+        if self.has(&ChatBadge::Subscriber) || self.has(&ChatBadge::Founder) {
+            self.subscriber_months
+        } else {
+            0
+        }
+    }
+
+    /**
+     * Prefix used when logging the sender of a message
+     */
+    pub fn prefix(&self) -> &'static str {
+        if self.is_broadcaster() {
+            "~"
+        } else if self.is_mod() {
+            "@"
+        } else if self.is_vip() {
+            "+"
+        } else {
+            ""
+        }
+    }
+}
+
+fn parse_badge_list(value: &str) -> Vec<Badge> {
+    value
+        .split(',')
+        .filter(|badge| !badge.is_empty())
+        .map(|badge| {
+            let (name, version) = badge.split_once('/').unwrap_or((badge, ""));
+            Badge {
+                name: name.to_string(),
+                version: version.to_string(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_badges() {
+        let badges = UserBadges::parse("broadcaster/1,subscriber/3012,artist-badge/1", "subscriber/14");
+        assert!(badges.is_broadcaster());
+        assert!(badges.has(&ChatBadge::Artist));
+        assert!(!badges.is_mod());
+        assert_eq!(badges.subscriber_months(), 14);
+        assert_eq!(badges.prefix(), "~");
+    }
+
+    #[test]
+    fn test_parse_empty_badges() {
+        let badges = UserBadges::parse("", "");
+        assert_eq!(badges, UserBadges::default());
+        assert_eq!(badges.subscriber_months(), 0);
+        assert_eq!(badges.prefix(), "");
+    }
+}
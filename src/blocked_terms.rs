@@ -0,0 +1,222 @@
+use entity::banned_words;
+use entity::blocked_term_links;
+use entity::sea_orm_active_enums::MatchMode;
+use std::collections::{HashMap, HashSet};
+
+/// How often blocked terms are synced
+pub const SYNC_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+
+/// Blocked terms fetched per request
+pub const TERMS_PER_PAGE: u32 = 100;
+
+/// Author of banned words imported from blocked terms
+pub const AUTHOR: &str = "twitch";
+
+/// Length limits of a blocked term, in characters
+const MIN_TERM_LENGTH: usize = 2;
+const MAX_TERM_LENGTH: usize = 500;
+
+/**
+ * A blocked term as listed by Helix
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteTerm {
+    pub id: String,
+    pub text: String,
+}
+
+/**
+ * A banned word of the channel
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalWord {
+    pub id: i32,
+    pub word: String,
+    /// Whether the word means the same as a blocked term and may be pushed
+    pub pushable: bool,
+}
+
+impl LocalWord {
+    /**
+     * Only plain, permanent words go to Twitch: it has no regexes or whole
+     * words, expiring words would outlive their expiry there and canaries are
+     * not meant to act on anyone yet
+     */
+    pub fn from_model(bw: &banned_words::Model) -> LocalWord {
+        let length = bw.word.chars().count();
+        LocalWord {
+            id: bw.id,
+            word: bw.word.clone(),
+            pushable: !bw.is_regex
+                && matches!(bw.match_mode, MatchMode::Substring | MatchMode::CaseInsensitive)
+                && bw.expires_at.is_none()
+                && !bw.canary
+                && !bw.word.contains('*')
+                && (MIN_TERM_LENGTH..=MAX_TERM_LENGTH).contains(&length),
+        }
+    }
+}
+
+/**
+ * Banned word pattern for a blocked term. Terms are case-insensitive and a
+ * `*` stands for the rest of a word, which takes a regex.
+ */
+pub fn term_rule(text: &str) -> (String, MatchMode) {
+    if !text.contains('*') {
+        return (text.to_string(), MatchMode::CaseInsensitive);
+    }
+    let pieces: Vec<String> = text.split('*').map(regex::escape).collect();
+    (format!("(?i){}", pieces.join(r"\S*")), MatchMode::Regex)
+}
+
+/**
+ * One step of a sync
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// Add the term as a banned word and link the two
+    Import(RemoteTerm),
+    /// Link a term and a word that already say the same
+    Link { term: RemoteTerm, word: LocalWord },
+    /// Add the word as a blocked term and link the two
+    Push(LocalWord),
+    /// Remove the linked term from Twitch and drop the link
+    DeleteRemote { link_id: i32, term_id: String },
+    /// Delete the linked banned word and drop the link
+    DeleteLocal { link_id: i32, word_id: i32 },
+    Unlink(i32),
+}
+
+/**
+ * Changes that bring the channel's blocked terms and banned words in line.
+ *
+ * Links remember both sides as they were when last synced, so a missing or
+ * changed side tells which one moved. A term removed on Twitch deletes its
+ * word unless the word was edited since, then the edit wins and the word is
+ * kept (and pushed again with `push`). A deleted word removes its term only
+ * with `push`; without it the link stays behind so that the term is not
+ * imported again.
+ */
+pub fn plan(remote: &[RemoteTerm], local: &[LocalWord], links: &[blocked_term_links::Model], push: bool) -> Vec<Change> {
+    let terms: HashMap<&str, &RemoteTerm> = remote.iter().map(|term| (term.id.as_str(), term)).collect();
+    let words: HashMap<i32, &LocalWord> = local.iter().map(|word| (word.id, word)).collect();
+    let linked_terms: HashSet<&str> = links.iter().map(|link| link.term_id.as_str()).collect();
+    let linked_words: HashSet<i32> = links.iter().filter_map(|link| link.banned_word_id).collect();
+
+    let mut changes = vec![];
+    for link in links {
+        let term = terms.get(link.term_id.as_str());
+        let word = link.banned_word_id.and_then(|id| words.get(&id));
+        let edited = word.is_some_and(|word| word.word != link.word);
+
+        match (term, word) {
+            (Some(_), Some(word)) if edited && push => {
+                changes.push(Change::DeleteRemote { link_id: link.id, term_id: link.term_id.clone() });
+                if word.pushable {
+                    changes.push(Change::Push((*word).clone()));
+                }
+            },
+            (Some(_), Some(_)) => {},
+            (Some(_), None) if push => changes.push(Change::DeleteRemote { link_id: link.id, term_id: link.term_id.clone() }),
+            (Some(_), None) => {},
+            (None, Some(word)) if edited => {
+                changes.push(Change::Unlink(link.id));
+                if push && word.pushable {
+                    changes.push(Change::Push((*word).clone()));
+                }
+            },
+            (None, Some(word)) => changes.push(Change::DeleteLocal { link_id: link.id, word_id: word.id }),
+            (None, None) => changes.push(Change::Unlink(link.id)),
+        }
+    }
+
+    let mut unlinked_words: Vec<&LocalWord> = local.iter().filter(|word| !linked_words.contains(&word.id)).collect();
+    for term in remote.iter().filter(|term| !linked_terms.contains(term.id.as_str())) {
+        match unlinked_words.iter().position(|word| word.word.to_lowercase() == term.text.to_lowercase()) {
+            Some(i) => changes.push(Change::Link { term: term.clone(), word: unlinked_words.remove(i).clone() }),
+            None => changes.push(Change::Import(term.clone())),
+        }
+    }
+    if push {
+        changes.extend(unlinked_words.into_iter().filter(|word| word.pushable).cloned().map(Change::Push));
+    }
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn term(id: &str, text: &str) -> RemoteTerm {
+        RemoteTerm { id: id.to_string(), text: text.to_string() }
+    }
+
+    fn word(id: i32, word: &str) -> LocalWord {
+        LocalWord { id, word: word.to_string(), pushable: true }
+    }
+
+    fn link(id: i32, term_id: &str, banned_word_id: Option<i32>, word: &str) -> blocked_term_links::Model {
+        blocked_term_links::Model {
+            id,
+            channel_id: 1,
+            term_id: term_id.to_string(),
+            term_text: word.to_string(),
+            banned_word_id,
+            word: word.to_string(),
+            created_at: Utc::now().fixed_offset(),
+            updated_at: Utc::now().fixed_offset(),
+        }
+    }
+
+    #[test]
+    fn test_plan_unlinked() {
+        let remote = [term("t1", "Cheap Viewers"), term("t2", "free followers")];
+        let local = [word(1, "cheap viewers"), word(2, "promo"), LocalWord { pushable: false, ..word(3, r"buy\s+now") }];
+
+        assert_eq!(plan(&remote, &local, &[], false), vec![
+            Change::Link { term: remote[0].clone(), word: local[0].clone() },
+            Change::Import(remote[1].clone()),
+        ]);
+        assert_eq!(plan(&remote, &local, &[], true).last(), Some(&Change::Push(local[1].clone())));
+        assert_eq!(plan(&remote, &local, &[], true).len(), 3);
+    }
+
+    #[test]
+    fn test_plan_linked() {
+        let remote = [term("t1", "promo"), term("t2", "drama"), term("t3", "spam")];
+        let local = [word(1, "promo"), word(2, "dramaa"), word(4, "scam")];
+        let links = [
+            link(1, "t1", Some(1), "promo"),
+            link(2, "t2", Some(2), "drama"),
+            link(3, "t3", None, "spam"),
+            link(4, "t4", Some(4), "scam"),
+            link(5, "t5", Some(5), "gone"),
+        ];
+
+        // Unchanged and deleted-word links stay, a term removed on Twitch deletes its word
+        assert_eq!(plan(&remote, &local, &links, false), vec![
+            Change::DeleteLocal { link_id: 4, word_id: 4 },
+            Change::Unlink(5),
+        ]);
+        assert_eq!(plan(&remote, &local, &links, true), vec![
+            Change::DeleteRemote { link_id: 2, term_id: "t2".to_string() },
+            Change::Push(local[1].clone()),
+            Change::DeleteRemote { link_id: 3, term_id: "t3".to_string() },
+            Change::DeleteLocal { link_id: 4, word_id: 4 },
+            Change::Unlink(5),
+        ]);
+
+        // Both sides changed: the word is kept and pushed again
+        let links = [link(2, "t9", Some(2), "drama")];
+        assert_eq!(plan(&[], &local[1..2], &links, true), vec![Change::Unlink(2), Change::Push(local[1].clone())]);
+        assert_eq!(plan(&[], &local[1..2], &links, false), vec![Change::Unlink(2)]);
+    }
+
+    #[test]
+    fn test_term_rule() {
+        assert_eq!(term_rule("Cheap Viewers"), ("Cheap Viewers".to_string(), MatchMode::CaseInsensitive));
+        assert_eq!(term_rule("*c.om"), (r"(?i)\S*c\.om".to_string(), MatchMode::Regex));
+    }
+}
@@ -0,0 +1,281 @@
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// How often the configured bot lists are imported again
+pub const IMPORT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// Users written per statement
+pub const IMPORT_BATCH: usize = 1000;
+
+/// Fields holding the login in JSON lists of objects
+const LOGIN_FIELDS: [&str; 4] = ["login", "user_login", "username", "name"];
+
+/**
+ * A known-bot list file and the source tag its entries are stored with
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BotList {
+    pub source: String,
+    pub path: PathBuf,
+}
+
+impl BotList {
+    /**
+     * Parse `source=path`, or a bare path tagged with its file name
+     */
+    pub fn parse(spec: &str) -> BotList {
+        match spec.split_once('=') {
+            Some((source, path)) if !source.is_empty() => BotList {
+                source: source.to_string(),
+                path: PathBuf::from(path),
+            },
+            _ => BotList {
+                source: Path::new(spec).file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_else(|| spec.to_string()),
+                path: PathBuf::from(spec),
+            },
+        }
+    }
+
+    pub fn format(&self) -> ListFormat {
+        match self.path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("csv") => ListFormat::Csv,
+            Some(extension) if extension.eq_ignore_ascii_case("json") => ListFormat::Json,
+            _ => ListFormat::Text,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListFormat {
+    /// One login per line, `#` starts a comment
+    Text,
+    /// Login in the first column, a header row is skipped
+    Csv,
+    /// Array of logins, of arrays starting with the login or of objects with a
+    /// login field, either at the top level or under `bots`, `users` or `data`
+    Json,
+}
+
+/**
+ * Logins read from a list and the entries that were not valid logins
+ */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParsedList {
+    pub logins: HashSet<String>,
+    pub invalid: usize,
+}
+
+impl ParsedList {
+    fn push(&mut self, entry: &str) {
+        let login = entry.trim().trim_start_matches('@').to_lowercase();
+        if is_login(&login) {
+            self.logins.insert(login);
+        } else {
+            self.invalid += 1;
+        }
+    }
+}
+
+fn is_login(login: &str) -> bool {
+    (1..=25).contains(&login.len()) && login.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+pub fn parse_list(content: &str, format: ListFormat) -> Result<ParsedList, String> {
+    let mut list = ParsedList::default();
+    match format {
+        ListFormat::Text => {
+            for line in content.lines() {
+                let line = line.split('#').next().unwrap_or_default().trim();
+                if !line.is_empty() {
+                    list.push(line);
+                }
+            }
+        },
+        ListFormat::Csv => {
+            for (i, line) in content.lines().enumerate() {
+                let cell = line.split(',').next().unwrap_or_default().trim().trim_matches('"');
+                let is_header = i == 0 && LOGIN_FIELDS.iter().any(|field| cell.eq_ignore_ascii_case(field));
+                if !cell.is_empty() && !is_header {
+                    list.push(cell);
+                }
+            }
+        },
+        ListFormat::Json => {
+            let value: Value = serde_json::from_str(content).map_err(|e| e.to_string())?;
+            let entries = match &value {
+                Value::Array(entries) => entries,
+                Value::Object(object) => ["bots", "users", "data"].iter()
+                    .find_map(|key| object.get(*key).and_then(Value::as_array))
+                    .ok_or("expected an array of bots")?,
+                _ => return Err("expected an array of bots".to_string()),
+            };
+            for entry in entries {
+                let login = match entry {
+                    Value::String(login) => Some(login.as_str()),
+                    Value::Array(fields) => fields.first().and_then(Value::as_str),
+                    Value::Object(object) => LOGIN_FIELDS.iter().find_map(|field| object.get(*field).and_then(Value::as_str)),
+                    _ => None,
+                };
+                match login {
+                    Some(login) => list.push(login),
+                    None => list.invalid += 1,
+                }
+            }
+        },
+    }
+    Ok(list)
+}
+
+/**
+ * How a user is currently flagged, `source` is `None` when a human set `is_bot`
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserFlag {
+    pub is_bot: bool,
+    pub source: Option<String>,
+}
+
+/**
+ * Changes that bring `users.is_bot` in line with the imported lists
+ */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportPlan {
+    /// Logins without a user row, per source
+    pub insert: HashMap<String, Vec<String>>,
+    /// Existing users to flag or refresh, per source
+    pub flag: HashMap<String, Vec<String>>,
+    /// Users no longer on the list that flagged them
+    pub unflag: Vec<String>,
+    pub stats: ImportStats,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportStats {
+    pub listed: usize,
+    pub invalid: usize,
+    /// Users that were not flagged before, new or existing
+    pub flagged: usize,
+    /// Users an earlier import had flagged already
+    pub refreshed: usize,
+    /// Listed users a human had flagged, left untouched
+    pub human: usize,
+    pub unflagged: usize,
+}
+
+impl fmt::Display for ImportStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} listed ({} invalid entries skipped), {} newly flagged, {} still flagged, {} flagged by hand, {} unflagged",
+            self.listed, self.invalid, self.flagged, self.refreshed, self.human, self.unflagged,
+        )
+    }
+}
+
+/**
+ * Compare the lists with the users' current flags. A login on several lists
+ * is credited to the first one. Users flagged by one of these sources and no
+ * longer on any of the lists are unflagged, users a human flagged are never
+ * touched.
+ */
+pub fn plan_import(lists: &[(String, ParsedList)], users: &HashMap<String, UserFlag>) -> ImportPlan {
+    let sources: HashSet<&str> = lists.iter().map(|(source, _)| source.as_str()).collect();
+    let mut plan = ImportPlan::default();
+    let mut listed: HashSet<&str> = HashSet::new();
+
+    for (source, list) in lists {
+        plan.stats.invalid += list.invalid;
+        let mut logins: Vec<&String> = list.logins.iter().filter(|login| listed.insert(login.as_str())).collect();
+        logins.sort();
+
+        for login in logins {
+            match users.get(login.as_str()) {
+                None => {
+                    plan.stats.flagged += 1;
+                    plan.insert.entry(source.clone()).or_default().push(login.clone());
+                },
+                Some(UserFlag { is_bot: true, source: None }) => plan.stats.human += 1,
+                Some(flag) => {
+                    if flag.is_bot {
+                        plan.stats.refreshed += 1;
+                    } else {
+                        plan.stats.flagged += 1;
+                    }
+                    plan.flag.entry(source.clone()).or_default().push(login.clone());
+                },
+            }
+        }
+    }
+    plan.stats.listed = listed.len();
+
+    plan.unflag = users.iter()
+        .filter(|(login, flag)| {
+            flag.is_bot
+                && flag.source.as_deref().is_some_and(|source| sources.contains(source))
+                && !listed.contains(login.as_str())
+        })
+        .map(|(login, _)| login.clone())
+        .collect();
+    plan.unflag.sort();
+    plan.stats.unflagged = plan.unflag.len();
+
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bot_list_spec() {
+        assert_eq!(BotList::parse("insights=/lists/bots.json"), BotList { source: "insights".to_string(), path: PathBuf::from("/lists/bots.json") });
+        let list = BotList::parse("/lists/known_bots.CSV");
+        assert_eq!(list.source, "known_bots");
+        assert_eq!(list.format(), ListFormat::Csv);
+        assert_eq!(BotList::parse("bots.txt").format(), ListFormat::Text);
+    }
+
+    #[test]
+    fn test_parse_list() {
+        let text = parse_list("# known bots\nSpam_Bot\n\n@viewerbot # added by hand\nnot a login\n", ListFormat::Text).unwrap();
+        assert_eq!(text.logins, HashSet::from(["spam_bot".to_string(), "viewerbot".to_string()]));
+        assert_eq!(text.invalid, 1);
+
+        let csv = parse_list("username,first_seen\nspam_bot,2024-01-01\n\"viewerbot\",2024-02-01\n", ListFormat::Csv).unwrap();
+        assert_eq!(csv.logins.len(), 2);
+        assert_eq!(csv.invalid, 0);
+
+        let json = parse_list(r#"{"bots": [["spam_bot", 120, 1700000000], {"login": "ViewerBot"}, "lurker_bot", 42]}"#, ListFormat::Json).unwrap();
+        assert_eq!(json.logins, HashSet::from(["spam_bot".to_string(), "viewerbot".to_string(), "lurker_bot".to_string()]));
+        assert_eq!(json.invalid, 1);
+
+        assert!(parse_list(r#"{"count": 3}"#, ListFormat::Json).is_err());
+    }
+
+    #[test]
+    fn test_plan_import() {
+        let flag = |is_bot, source: Option<&str>| UserFlag { is_bot, source: source.map(str::to_string) };
+        let users = HashMap::from([
+            ("regular".to_string(), flag(false, None)),
+            ("manual_bot".to_string(), flag(true, None)),
+            ("old_bot".to_string(), flag(true, Some("insights"))),
+            ("listed_bot".to_string(), flag(true, Some("insights"))),
+            ("other_bot".to_string(), flag(true, Some("other_list"))),
+            ("manual_gone".to_string(), flag(true, None)),
+        ]);
+        let list = |logins: &[&str]| ParsedList { logins: logins.iter().map(|login| login.to_string()).collect(), invalid: 0 };
+        let lists = vec![
+            ("insights".to_string(), list(&["regular", "manual_bot", "listed_bot", "new_bot"])),
+            ("local".to_string(), list(&["new_bot", "another_bot"])),
+        ];
+
+        let plan = plan_import(&lists, &users);
+        assert_eq!(plan.insert["insights"], vec!["new_bot"]);
+        assert_eq!(plan.insert["local"], vec!["another_bot"]);
+        assert_eq!(plan.flag["insights"], vec!["listed_bot", "regular"]);
+        assert_eq!(plan.unflag, vec!["old_bot"]);
+        assert_eq!(plan.stats, ImportStats { listed: 5, invalid: 0, flagged: 3, refreshed: 1, human: 1, unflagged: 1 });
+    }
+}
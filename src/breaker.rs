@@ -0,0 +1,158 @@
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use crate::settings::BreakerLimit;
+
+/// Window of the global limit
+pub const GLOBAL_WINDOW_SECS: u32 = 60;
+
+/**
+ * Where the breaker tripped
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Scope {
+    Channel(String),
+    /// Across all channels, holds every channel
+    Global,
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scope::Channel(channel) => write!(f, "#{}", channel),
+            Scope::Global => f.write_str("all channels"),
+        }
+    }
+}
+
+/**
+ * Whether an action may be carried out
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Admission {
+    Allowed,
+    /// The breaker tripped earlier, the action is only recorded
+    Held,
+    /// The action tripped the breaker, with the actions in the window
+    Tripped(Scope, usize),
+}
+
+/**
+ * Counts enforcement actions per channel and across channels, and holds them
+ * once a limit is reached until a moderator resumes enforcement
+ */
+#[derive(Debug, Default)]
+pub struct CircuitBreaker {
+    channels: HashMap<String, VecDeque<DateTime<Utc>>>,
+    global: VecDeque<DateTime<Utc>>,
+    tripped: HashSet<String>,
+    global_tripped: bool,
+}
+
+impl CircuitBreaker {
+    pub fn is_tripped(&self, channel: &str) -> bool {
+        self.global_tripped || self.tripped.contains(channel)
+    }
+
+    /**
+     * Count an action in the channel unless it would break the channel's
+     * limit or `global_max` actions per minute, 0 for no global limit
+     */
+    pub fn admit(&mut self, channel: &str, now: DateTime<Utc>, limit: &BreakerLimit, global_max: u32) -> Admission {
+        if self.is_tripped(channel) {
+            return Admission::Held;
+        }
+
+        let actions = self.channels.entry(channel.to_string()).or_default();
+        prune(actions, now - Duration::seconds(limit.window_secs.into()));
+        prune(&mut self.global, now - Duration::seconds(GLOBAL_WINDOW_SECS.into()));
+
+        if limit.enabled && actions.len() >= limit.max_actions as usize {
+            self.tripped.insert(channel.to_string());
+            return Admission::Tripped(Scope::Channel(channel.to_string()), actions.len() + 1);
+        }
+        if global_max > 0 && self.global.len() >= global_max as usize {
+            self.global_tripped = true;
+            return Admission::Tripped(Scope::Global, self.global.len() + 1);
+        }
+
+        actions.push_back(now);
+        self.global.push_back(now);
+        Admission::Allowed
+    }
+
+    pub fn is_tripped_globally(&self) -> bool {
+        self.global_tripped
+    }
+
+    /**
+     * Resume enforcement in a channel. A global trip is left in place, see
+     * [`CircuitBreaker::resume_global`]. The channel's counted actions are
+     * forgotten so that they do not trip it again. Returns whether the channel
+     * limit had tripped.
+     */
+    pub fn resume(&mut self, channel: &str) -> bool {
+        self.channels.remove(channel);
+        self.tripped.remove(channel)
+    }
+
+    /**
+     * Resume enforcement in every channel after the global limit tripped.
+     * Returns whether it had tripped.
+     */
+    pub fn resume_global(&mut self) -> bool {
+        self.global.clear();
+        std::mem::take(&mut self.global_tripped)
+    }
+}
+
+fn prune(actions: &mut VecDeque<DateTime<Utc>>, since: DateTime<Utc>) {
+    while actions.front().is_some_and(|at| *at <= since) {
+        actions.pop_front();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_admit() {
+        let now = Utc::now();
+        let limit = BreakerLimit { enabled: true, max_actions: 2, window_secs: 60 };
+        let mut breaker = CircuitBreaker::default();
+
+        assert_eq!(breaker.admit("channel", now, &limit, 0), Admission::Allowed);
+        assert_eq!(breaker.admit("channel", now, &limit, 0), Admission::Allowed);
+        assert_eq!(breaker.admit("channel", now, &limit, 0), Admission::Tripped(Scope::Channel("channel".to_string()), 3));
+        assert_eq!(breaker.admit("channel", now + Duration::minutes(5), &limit, 0), Admission::Held);
+        assert_eq!(breaker.admit("other", now, &limit, 0), Admission::Allowed);
+
+        assert!(breaker.resume("channel"));
+        assert!(!breaker.resume("channel"));
+        assert_eq!(breaker.admit("channel", now, &limit, 0), Admission::Allowed);
+
+        // Older actions fall out of the window
+        assert_eq!(breaker.admit("channel", now + Duration::seconds(61), &limit, 0), Admission::Allowed);
+        assert_eq!(breaker.admit("channel", now + Duration::seconds(62), &limit, 0), Admission::Allowed);
+    }
+
+    #[test]
+    fn test_global_limit() {
+        let now = Utc::now();
+        let limit = BreakerLimit { enabled: false, ..Default::default() };
+        let mut breaker = CircuitBreaker::default();
+
+        assert_eq!(breaker.admit("a", now, &limit, 2), Admission::Allowed);
+        assert_eq!(breaker.admit("b", now, &limit, 2), Admission::Allowed);
+        assert_eq!(breaker.admit("c", now, &limit, 2), Admission::Tripped(Scope::Global, 3));
+        assert!(breaker.is_tripped("a"));
+
+        // A channel's mods cannot lift a trip that holds every channel
+        assert!(!breaker.resume("b"));
+        assert!(breaker.is_tripped("b"));
+        assert!(breaker.resume_global());
+        assert!(!breaker.is_tripped("a"));
+        assert!(!breaker.resume_global());
+    }
+}
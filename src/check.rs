@@ -0,0 +1,242 @@
+use chrono::{DateTime, Utc};
+use entity::sea_orm_active_enums::{MatchMode, RuleAction};
+use sea_orm::ActiveEnum;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use crate::matcher::{Rule, RuleError};
+use crate::moderation::{Action, DecisionTrace};
+use crate::normalize::NormalizeOverrides;
+
+/**
+ * A message from the log to check
+ */
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct LogMessage {
+    /// Channel it was posted in, `--channel` when unset
+    #[serde(default)]
+    pub channel: Option<String>,
+    #[serde(default, alias = "user", alias = "username")]
+    pub login: String,
+    #[serde(default)]
+    pub user_id: Option<String>,
+    #[serde(alias = "message")]
+    pub text: String,
+    /// `badges` tag as in IRC, e.g. `vip/1,subscriber/12`
+    #[serde(default)]
+    pub badges: String,
+    #[serde(default)]
+    pub badge_info: String,
+    /// Whether the author posted before, known users and earlier messages in the log decide when unset
+    #[serde(default)]
+    pub seen: Option<bool>,
+    #[serde(default, alias = "timestamp")]
+    pub at: Option<DateTime<Utc>>,
+}
+
+/**
+ * Parse a message log, JSON objects or plain text one per line, with their
+ * line numbers. Plain lines are messages of a user named after the line.
+ */
+pub fn parse_log(content: &str) -> Result<Vec<(usize, LogMessage)>, String> {
+    let mut messages = vec![];
+    for (i, line) in content.lines().enumerate() {
+        let number = i + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let mut message = if line.starts_with('{') {
+            serde_json::from_str(line).map_err(|e| format!("line {}: {}", number, e))?
+        } else {
+            LogMessage { text: line.to_string(), ..Default::default() }
+        };
+        if message.login.is_empty() {
+            message.login = format!("line{}", number);
+        }
+        message.login = message.login.to_lowercase();
+        messages.push((number, message));
+    }
+    Ok(messages)
+}
+
+/**
+ * A banned word in a rules file, like a `banned_words` row
+ */
+#[derive(Debug, Deserialize)]
+struct RuleLine {
+    id: Option<i32>,
+    #[serde(alias = "pattern")]
+    word: String,
+    match_mode: Option<String>,
+    #[serde(default)]
+    is_regex: bool,
+    #[serde(default)]
+    normalization: serde_json::Value,
+    severity: Option<i32>,
+    action: Option<String>,
+    timeout_duration: Option<u32>,
+    #[serde(default)]
+    canary: bool,
+    channel: Option<String>,
+}
+
+/**
+ * Parse banned words from a rules file. Lines are JSON objects like
+ * `banned_words` rows or plain words, `/regex/` for a regex, and `#` starts a
+ * comment. Rules without an ID are numbered by their line.
+ */
+pub fn parse_rules(content: &str) -> Result<Vec<Rule>, String> {
+    let mut rules = vec![];
+    for (i, line) in content.lines().enumerate() {
+        let number = i + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if !line.starts_with('{') {
+            rules.push(match line.strip_prefix('/').and_then(|pattern| pattern.strip_suffix('/')) {
+                Some(pattern) => Rule::new(number as i32, pattern, MatchMode::Regex),
+                None => Rule::new(number as i32, line, MatchMode::Substring),
+            });
+            continue;
+        }
+
+        let spec: RuleLine = serde_json::from_str(line).map_err(|e| format!("line {}: {}", number, e))?;
+        let mode = match &spec.match_mode {
+            _ if spec.is_regex => MatchMode::Regex,
+            Some(mode) => MatchMode::try_from_value(mode).map_err(|_| format!("line {}: unknown match mode {}", number, mode))?,
+            None => MatchMode::Substring,
+        };
+        let action = spec.action
+            .map(|action| RuleAction::try_from_value(&action).map_err(|_| format!("line {}: unknown action {}", number, action)))
+            .transpose()?;
+        let normalize = NormalizeOverrides::from_json(&spec.normalization)
+            .map_err(|e| format!("line {}: invalid normalization: {}", number, e))?
+            .resolve(mode == MatchMode::Regex);
+
+        rules.push(Rule {
+            normalize,
+            severity: spec.severity.unwrap_or(1),
+            action,
+            timeout_duration: spec.timeout_duration,
+            canary: spec.canary,
+            channel: spec.channel.map(|channel| channel.to_lowercase()),
+            ..Rule::new(spec.id.unwrap_or(number as i32), &spec.word, mode)
+        });
+    }
+    Ok(rules)
+}
+
+/**
+ * Line of a report about a banned word that was not accepted
+ */
+pub fn rejection(rule: &Rule, e: &RuleError) -> String {
+    format!("rule #{} \"{}\" rejected: {}", rule.id, rule.pattern, e)
+}
+
+/**
+ * What the pipeline decided on one message
+ */
+#[derive(Debug, Clone)]
+pub struct Verdict {
+    pub line: usize,
+    pub channel: String,
+    pub message: LogMessage,
+    pub action: Option<Action>,
+    pub trace: DecisionTrace,
+}
+
+/**
+ * Decisions on a whole log
+ */
+#[derive(Debug, Default)]
+pub struct Report {
+    /// Rules left out, see [`rejection`]
+    pub rejected: Vec<String>,
+    pub verdicts: Vec<Verdict>,
+}
+
+impl Report {
+    pub fn actioned(&self) -> impl Iterator<Item = &Verdict> {
+        self.verdicts.iter().filter(|verdict| verdict.action.is_some())
+    }
+
+    /**
+     * Messages each deciding rule actioned, by rule ID, with its pattern
+     */
+    pub fn rule_hits(&self) -> BTreeMap<i32, (String, usize)> {
+        let mut hits: BTreeMap<i32, (String, usize)> = BTreeMap::new();
+        for verdict in self.actioned() {
+            let Some(rule_id) = verdict.trace.decided_by else {
+                continue;
+            };
+            let pattern = verdict.trace.matches.iter()
+                .find(|rule_match| rule_match.rule_id == rule_id)
+                .map(|rule_match| rule_match.pattern.clone())
+                .unwrap_or_default();
+            hits.entry(rule_id).or_insert((pattern, 0)).1 += 1;
+        }
+        hits
+    }
+
+    /**
+     * Rejected rules, then actioned messages with how they were decided, every
+     * message with `all`, followed by the totals and the hits per rule
+     */
+    pub fn render(&self, all: bool) -> String {
+        let mut out = String::new();
+        for rejection in &self.rejected {
+            let _ = writeln!(out, "{}", rejection);
+        }
+        for verdict in self.verdicts.iter().filter(|verdict| all || verdict.action.is_some()) {
+            let channel = if verdict.channel.is_empty() { String::new() } else { format!("#{} ", verdict.channel) };
+            let _ = writeln!(out, "line {}: {}<{}> {}", verdict.line, channel, verdict.message.login, verdict.message.text);
+            let _ = writeln!(out, "    {}", verdict.trace.summary());
+        }
+
+        let _ = writeln!(out, "{} of {} messages would be actioned", self.actioned().count(), self.verdicts.len());
+        for (rule_id, (pattern, hits)) in self.rule_hits() {
+            let _ = writeln!(out, "rule #{} \"{}\": {} messages", rule_id, pattern, hits);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_log() {
+        let messages = parse_log(concat!(
+            "buy followers at example.com\n",
+            "\n",
+            r#"{"channel": "test_channel", "user": "SomeUser", "message": "hi", "badges": "vip/1", "seen": true}"#, "\n",
+        )).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!((messages[0].0, messages[0].1.login.as_str()), (1, "line1"));
+        assert_eq!(messages[1].0, 3);
+        assert_eq!(messages[1].1.login, "someuser");
+        assert_eq!(messages[1].1.seen, Some(true));
+        assert!(parse_log("{\"login\": \"a\"}").unwrap_err().starts_with("line 1:"));
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        let rules = parse_rules(concat!(
+            "# comment\n",
+            "cheap viewers\n",
+            "/buy\\s+followers/\n",
+            r#"{"id": 7, "word": "promo", "match_mode": "whole_word", "action": "timeout", "timeout_duration": 60, "channel": "Test_Channel"}"#, "\n",
+        )).unwrap();
+        assert_eq!(rules.len(), 3);
+        assert_eq!((rules[0].id, rules[0].mode), (2, MatchMode::Substring));
+        assert_eq!((rules[1].pattern.as_str(), rules[1].mode), ("buy\\s+followers", MatchMode::Regex));
+        assert_eq!((rules[2].id, rules[2].mode, rules[2].action), (7, MatchMode::WholeWord, Some(RuleAction::Timeout)));
+        assert_eq!(rules[2].channel.as_deref(), Some("test_channel"));
+        assert!(parse_rules(r#"{"word": "a", "match_mode": "fuzzy"}"#).is_err());
+    }
+}
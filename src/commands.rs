@@ -0,0 +1,104 @@
+use crate::nuke::NukeSpec;
+
+/**
+ * Chat commands accepted from moderators
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// `!banword <phrase>`
+    BanWord(String),
+    /// `!banregex <pattern>`
+    BanRegex(String),
+    /// `!history <user>`
+    History(String),
+    /// `!why <user>`
+    Why(String),
+    /// `!unban <user>`
+    Unban(String),
+    /// `!precision [rule id]`
+    Precision(Option<i32>),
+    /// `!banwave`, ban the latest spam wave's phrase for a while
+    BanWave,
+    /// `!suggestions`, phrases and URLs learned from mod actions
+    Suggestions,
+    /// `!approve <n>`, ban the n-th listed suggestion
+    Approve(usize),
+    /// `!reject <n>`, never suggest the n-th listed suggestion again
+    Reject(usize),
+    /// `!nuke <phrase|/regex/> [lookback] [action]`, preview acting on every recent match
+    Nuke(NukeSpec),
+    /// `!nuke confirm`, carry out the previewed nuke
+    ConfirmNuke,
+    /// `!unnuke`, reverse the latest nuke
+    Unnuke,
+    /// `!resume`, enforce again after the circuit breaker tripped
+    Resume,
+}
+
+impl Command {
+    /**
+     * Parse a chat message into a command, `None` for anything else
+     */
+    pub fn parse(text: &str) -> Option<Command> {
+        let text = text.trim();
+        let (name, args) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        let args = args.trim();
+
+        match name.strip_prefix('!')? {
+            "banword" if !args.is_empty() => Some(Command::BanWord(args.to_string())),
+            "banregex" if !args.is_empty() => Some(Command::BanRegex(args.to_string())),
+            "history" if !args.is_empty() => Some(Command::History(user_arg(args))),
+            "why" if !args.is_empty() => Some(Command::Why(user_arg(args))),
+            "unban" if !args.is_empty() => Some(Command::Unban(user_arg(args))),
+            "banwave" => Some(Command::BanWave),
+            "suggestions" => Some(Command::Suggestions),
+            "approve" => args.trim_start_matches('#').parse().ok().map(Command::Approve),
+            "reject" => args.trim_start_matches('#').parse().ok().map(Command::Reject),
+            "nuke" if args == "confirm" => Some(Command::ConfirmNuke),
+            "nuke" => NukeSpec::parse(args).map(Command::Nuke),
+            "unnuke" => Some(Command::Unnuke),
+            "resume" => Some(Command::Resume),
+            "precision" if args.is_empty() => Some(Command::Precision(None)),
+            "precision" => args.trim_start_matches('#').parse().ok().map(|id| Command::Precision(Some(id))),
+            _ => None,
+        }
+    }
+}
+
+/**
+ * Login from a user argument, accepting `@Name` as well
+ */
+fn user_arg(args: &str) -> String {
+    let user = args.split_whitespace().next().unwrap_or_default();
+    user.trim_start_matches('@').to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Command::parse("!banword cheap viewers "), Some(Command::BanWord("cheap viewers".to_string())));
+        assert_eq!(Command::parse("!banregex buy\\s+followers"), Some(Command::BanRegex("buy\\s+followers".to_string())));
+        assert_eq!(Command::parse("!history @SomeUser"), Some(Command::History("someuser".to_string())));
+        assert_eq!(Command::parse("!why spammer"), Some(Command::Why("spammer".to_string())));
+        assert_eq!(Command::parse("!unban @Someone"), Some(Command::Unban("someone".to_string())));
+        assert_eq!(Command::parse("!precision #3"), Some(Command::Precision(Some(3))));
+        assert_eq!(Command::parse("!precision"), Some(Command::Precision(None)));
+        assert_eq!(Command::parse("!precision three"), None);
+        assert_eq!(Command::parse("!banwave"), Some(Command::BanWave));
+        assert_eq!(Command::parse("!suggestions"), Some(Command::Suggestions));
+        assert_eq!(Command::parse("!approve #2"), Some(Command::Approve(2)));
+        assert_eq!(Command::parse("!reject 1"), Some(Command::Reject(1)));
+        assert_eq!(Command::parse("!approve"), None);
+        assert!(matches!(Command::parse("!nuke buy followers 10m ban"), Some(Command::Nuke(spec)) if spec.pattern == "buy followers"));
+        assert_eq!(Command::parse("!nuke confirm"), Some(Command::ConfirmNuke));
+        assert_eq!(Command::parse("!nuke"), None);
+        assert_eq!(Command::parse("!unnuke"), Some(Command::Unnuke));
+        assert_eq!(Command::parse("!resume"), Some(Command::Resume));
+        assert_eq!(Command::parse("!banword"), None);
+        assert_eq!(Command::parse("banword test"), None);
+        assert_eq!(Command::parse("!unknown test"), None);
+    }
+}
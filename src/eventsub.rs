@@ -0,0 +1,456 @@
+use chrono::{DateTime, Utc};
+use futures_util::{SinkExt, StreamExt};
+use reqwest::header::CONTENT_TYPE;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::Sender;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tracing::{debug, info, warn};
+use crate::bot::BotEvent;
+use crate::helix::HelixError;
+
+/// Helix endpoint for creating EventSub subscriptions
+pub const SUBSCRIPTIONS_URL: &str = "https://api.twitch.tv/helix/eventsub/subscriptions";
+
+/// Keepalive timeout until the welcome message says otherwise
+const DEFAULT_KEEPALIVE: Duration = Duration::from_secs(10);
+
+/// Silence allowed on top of the keepalive timeout before the session is considered dead
+const KEEPALIVE_GRACE: Duration = Duration::from_secs(5);
+
+const MIN_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+/// Message IDs remembered for dropping notifications Twitch sends twice
+const SEEN_MESSAGE_IDS: usize = 256;
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/**
+ * A user banned or timed out in a channel, by anyone
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct BanEvent {
+    pub broadcaster_user_id: String,
+    pub broadcaster_user_login: String,
+    pub user_id: String,
+    pub user_login: String,
+    pub moderator_user_login: String,
+    #[serde(default)]
+    pub reason: String,
+    pub ends_at: Option<DateTime<Utc>>,
+    pub is_permanent: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct UnbanEvent {
+    pub broadcaster_user_login: String,
+    pub user_id: String,
+    pub user_login: String,
+    pub moderator_user_login: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct FollowEvent {
+    pub broadcaster_user_login: String,
+    pub user_id: String,
+    pub user_login: String,
+    pub followed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct StreamEvent {
+    pub broadcaster_user_id: String,
+    pub broadcaster_user_login: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct MessageBody {
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ChatMessageEvent {
+    pub broadcaster_user_login: String,
+    pub chatter_user_id: String,
+    pub chatter_user_login: String,
+    pub message_id: String,
+    pub message: MessageBody,
+}
+
+/**
+ * A message AutoMod held back for review
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct AutomodHoldEvent {
+    pub broadcaster_user_id: String,
+    pub broadcaster_user_login: String,
+    pub user_id: String,
+    pub user_login: String,
+    pub message_id: String,
+    pub message: MessageBody,
+    pub category: String,
+    pub level: u8,
+}
+
+/**
+ * Bot event for an EventSub notification, `None` for subscription types the
+ * bot does not handle
+ */
+pub fn parse_notification(subscription_type: &str, event: Value) -> Result<Option<BotEvent>, serde_json::Error> {
+    let event = match subscription_type {
+        "channel.ban" => BotEvent::Ban(serde_json::from_value(event)?),
+        "channel.unban" => BotEvent::Unban(serde_json::from_value(event)?),
+        "channel.follow" => BotEvent::Follow(serde_json::from_value(event)?),
+        "stream.online" => BotEvent::StreamOnline(serde_json::from_value(event)?),
+        "stream.offline" => BotEvent::StreamOffline(serde_json::from_value(event)?),
+        "channel.chat.message" => BotEvent::ChatMessage(serde_json::from_value(event)?),
+        "automod.message.hold" => BotEvent::AutomodHold(serde_json::from_value(event)?),
+        _ => return Ok(None),
+    };
+    Ok(Some(event))
+}
+
+/**
+ * Subscription requests for everything the bot handles in a channel. The
+ * transport is the `transport` object of the request, e.g. from `websocket_transport`.
+ */
+pub fn subscriptions(transport: &Value, broadcaster_id: &str, bot_user_id: &str) -> Vec<Value> {
+    let broadcaster = json!({ "broadcaster_user_id": broadcaster_id });
+    let moderator = json!({ "broadcaster_user_id": broadcaster_id, "moderator_user_id": bot_user_id });
+    let chatter = json!({ "broadcaster_user_id": broadcaster_id, "user_id": bot_user_id });
+
+    [
+        ("channel.ban", "1", &broadcaster),
+        ("channel.unban", "1", &broadcaster),
+        ("channel.follow", "2", &moderator),
+        ("stream.online", "1", &broadcaster),
+        ("stream.offline", "1", &broadcaster),
+        ("channel.chat.message", "1", &chatter),
+        ("automod.message.hold", "1", &moderator),
+    ]
+        .into_iter()
+        .map(|(subscription_type, version, condition)| json!({
+            "type": subscription_type,
+            "version": version,
+            "condition": condition,
+            "transport": transport,
+        }))
+        .collect()
+}
+
+pub fn websocket_transport(session_id: &str) -> Value {
+    json!({ "method": "websocket", "session_id": session_id })
+}
+
+/**
+ * Create a subscription through Helix, one that already exists counts as created
+ */
+pub async fn subscribe(http: &reqwest::Client, client_id: &str, token: &str, subscription: &Value) -> Result<(), HelixError> {
+    let response = http.post(SUBSCRIPTIONS_URL)
+        .header("Client-Id", client_id)
+        .header(CONTENT_TYPE, "application/json")
+        .bearer_auth(token)
+        .body(subscription.to_string())
+        .send()
+        .await
+        .map_err(|e| HelixError::unavailable(&e.to_string()))?;
+
+    let status = response.status();
+    if status.is_success() || status == reqwest::StatusCode::CONFLICT {
+        return Ok(());
+    }
+    Err(HelixError {
+        message: response.text().await.unwrap_or_default(),
+        status: Some(status.as_u16()),
+        retry_at: None,
+        permanent: false,
+    })
+}
+
+/**
+ * A message on an EventSub WebSocket, the ones the session loop cares about
+ */
+#[allow(clippy::large_enum_variant)]
+enum Frame {
+    Welcome { session_id: String, keepalive: Duration },
+    Keepalive,
+    Notification { message_id: String, event: Option<BotEvent> },
+    Reconnect { url: String },
+    Revocation { subscription_type: String, status: String },
+}
+
+#[derive(Deserialize)]
+struct RawFrame {
+    metadata: RawMetadata,
+    #[serde(default)]
+    payload: Value,
+}
+
+#[derive(Deserialize)]
+struct RawMetadata {
+    message_id: String,
+    message_type: String,
+}
+
+fn parse_frame(text: &str) -> Result<Frame, String> {
+    let frame: RawFrame = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    let payload = frame.payload;
+    let string = |value: &Value| value.as_str().map(str::to_string);
+
+    match frame.metadata.message_type.as_str() {
+        "session_welcome" => Ok(Frame::Welcome {
+            session_id: string(&payload["session"]["id"]).ok_or("welcome without a session ID")?,
+            keepalive: payload["session"]["keepalive_timeout_seconds"].as_u64().map(Duration::from_secs).unwrap_or(DEFAULT_KEEPALIVE),
+        }),
+        "session_keepalive" => Ok(Frame::Keepalive),
+        "session_reconnect" => Ok(Frame::Reconnect {
+            url: string(&payload["session"]["reconnect_url"]).ok_or("reconnect without a URL")?,
+        }),
+        "notification" => {
+            let subscription_type = string(&payload["subscription"]["type"]).unwrap_or_default();
+            let event = parse_notification(&subscription_type, payload["event"].clone())
+                .map_err(|e| format!("invalid {} event: {}", subscription_type, e))?;
+            Ok(Frame::Notification { message_id: frame.metadata.message_id, event })
+        },
+        "revocation" => Ok(Frame::Revocation {
+            subscription_type: string(&payload["subscription"]["type"]).unwrap_or_default(),
+            status: string(&payload["subscription"]["status"]).unwrap_or_default(),
+        }),
+        message_type => Err(format!("unknown message type {}", message_type)),
+    }
+}
+
+/**
+ * Keep an EventSub WebSocket session open and forward its notifications to
+ * the bot. Dropped or silent sessions are replaced by new ones, each of which
+ * is announced with `BotEvent::EventSubSession` so that the bot subscribes
+ * again. Returns once the bot stops listening.
+ */
+pub async fn run(url: String, events: Sender<BotEvent>) {
+    let mut delay = MIN_RECONNECT_DELAY;
+    loop {
+        match tokio_tungstenite::connect_async(url.as_str()).await {
+            Ok((socket, _)) => {
+                info!("Connected to EventSub at {}", url);
+                match session(socket, &events).await {
+                    Ok(()) => return,
+                    Err(SessionError { message, welcomed }) => {
+                        warn!("EventSub session ended: {}", message);
+                        if welcomed {
+                            delay = MIN_RECONNECT_DELAY;
+                        }
+                    },
+                }
+            },
+            Err(e) => warn!("Failed to connect to EventSub at {}: {}", url, e),
+        }
+
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+    }
+}
+
+#[derive(Debug)]
+struct SessionError {
+    message: String,
+    /// Whether the session got as far as its welcome message
+    welcomed: bool,
+}
+
+/**
+ * Read a session until it fails. `Ok` means the bot is gone.
+ */
+async fn session(mut socket: Socket, events: &Sender<BotEvent>) -> Result<(), SessionError> {
+    let mut keepalive = DEFAULT_KEEPALIVE;
+    let mut welcomed = false;
+    let mut seen: VecDeque<String> = VecDeque::new();
+    let error = |message: String, welcomed: bool| SessionError { message, welcomed };
+
+    loop {
+        let frame = match next_frame(&mut socket, keepalive).await {
+            Ok(frame) => frame,
+            Err(message) => return Err(error(message, welcomed)),
+        };
+
+        match frame {
+            Frame::Welcome { session_id, keepalive: timeout } => {
+                keepalive = timeout;
+                welcomed = true;
+                info!("EventSub session {} started", session_id);
+                if events.send(BotEvent::EventSubSession(session_id)).await.is_err() {
+                    return Ok(());
+                }
+            },
+            Frame::Keepalive => {},
+            Frame::Notification { message_id, event } => {
+                if seen.contains(&message_id) {
+                    debug!("Dropping EventSub message {} received twice", message_id);
+                    continue;
+                }
+                seen.push_back(message_id);
+                if seen.len() > SEEN_MESSAGE_IDS {
+                    seen.pop_front();
+                }
+                if let Some(event) = event {
+                    if events.send(event).await.is_err() {
+                        return Ok(());
+                    }
+                }
+            },
+            Frame::Reconnect { url } => {
+                // Subscriptions move to the new session, which is used once it has welcomed us
+                info!("EventSub asked to reconnect to {}", url);
+                let (mut new_socket, _) = tokio_tungstenite::connect_async(url.as_str()).await
+                    .map_err(|e| error(format!("failed to reconnect: {}", e), welcomed))?;
+                match next_frame(&mut new_socket, keepalive).await {
+                    Ok(Frame::Welcome { session_id, keepalive: timeout }) => {
+                        info!("EventSub session {} took over", session_id);
+                        keepalive = timeout;
+                    },
+                    Ok(_) => return Err(error("expected a welcome after reconnecting".to_string(), welcomed)),
+                    Err(message) => return Err(error(message, welcomed)),
+                }
+                let _ = socket.close(None).await;
+                socket = new_socket;
+            },
+            Frame::Revocation { subscription_type, status } => {
+                warn!("EventSub revoked the {} subscription: {}", subscription_type, status);
+            },
+        }
+    }
+}
+
+/**
+ * Next EventSub message, answering pings on the way. Fails when nothing
+ * arrives within the keepalive timeout.
+ */
+async fn next_frame(socket: &mut Socket, keepalive: Duration) -> Result<Frame, String> {
+    loop {
+        let message = tokio::time::timeout(keepalive + KEEPALIVE_GRACE, socket.next()).await
+            .map_err(|_| format!("no message within {}s", (keepalive + KEEPALIVE_GRACE).as_secs()))?;
+        let text = match message {
+            Some(Ok(Message::Text(text))) => text,
+            Some(Ok(Message::Ping(data))) => {
+                socket.send(Message::Pong(data)).await.map_err(|e| e.to_string())?;
+                continue;
+            },
+            Some(Ok(Message::Close(frame))) => return Err(format!("closed by Twitch: {:?}", frame)),
+            Some(Ok(_)) => continue,
+            Some(Err(e)) => return Err(e.to_string()),
+            None => return Err("connection closed".to_string()),
+        };
+
+        match parse_frame(text.as_str()) {
+            Ok(frame) => return Ok(frame),
+            Err(e) => warn!("Ignoring EventSub message: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    fn welcome(session_id: &str, keepalive: u64) -> Message {
+        Message::text(json!({
+            "metadata": { "message_id": format!("welcome-{}", session_id), "message_type": "session_welcome", "message_timestamp": "2026-10-19T10:00:00Z" },
+            "payload": { "session": { "id": session_id, "status": "connected", "keepalive_timeout_seconds": keepalive, "reconnect_url": null } },
+        }).to_string())
+    }
+
+    fn ban(message_id: &str) -> Message {
+        Message::text(json!({
+            "metadata": { "message_id": message_id, "message_type": "notification", "message_timestamp": "2026-10-19T10:00:01Z", "subscription_type": "channel.ban", "subscription_version": "1" },
+            "payload": {
+                "subscription": { "id": "sub-1", "type": "channel.ban", "version": "1", "status": "enabled" },
+                "event": {
+                    "user_id": "123", "user_login": "spammer", "user_name": "Spammer",
+                    "broadcaster_user_id": "1", "broadcaster_user_login": "test_channel", "broadcaster_user_name": "Test_Channel",
+                    "moderator_user_id": "2", "moderator_user_login": "a_mod", "moderator_user_name": "A_Mod",
+                    "reason": "spam", "banned_at": "2026-10-19T10:00:01Z", "ends_at": null, "is_permanent": true,
+                },
+            },
+        }).to_string())
+    }
+
+    #[test]
+    fn test_parse_notification() {
+        let event = json!({
+            "broadcaster_user_id": "1", "broadcaster_user_login": "test_channel",
+            "user_id": "123", "user_login": "spammer", "message_id": "abc",
+            "message": { "text": "cheap viewers", "fragments": [] },
+            "category": "swearing", "level": 3, "held_at": "2026-10-19T10:00:00Z",
+        });
+        let Ok(Some(BotEvent::AutomodHold(hold))) = parse_notification("automod.message.hold", event) else {
+            panic!("expected an AutoMod hold");
+        };
+        assert_eq!(hold.message.text, "cheap viewers");
+        assert_eq!(hold.level, 3);
+
+        assert!(parse_notification("channel.raid", json!({})).unwrap().is_none());
+        assert!(parse_notification("channel.ban", json!({ "user_id": "123" })).is_err());
+    }
+
+    #[test]
+    fn test_subscriptions() {
+        let subscriptions = subscriptions(&websocket_transport("session"), "1", "2");
+        assert_eq!(subscriptions.len(), 7);
+        assert_eq!(subscriptions[2]["type"], "channel.follow");
+        assert_eq!(subscriptions[2]["version"], "2");
+        assert_eq!(subscriptions[2]["condition"]["moderator_user_id"], "2");
+        assert_eq!(subscriptions[6]["transport"]["session_id"], "session");
+    }
+
+    #[tokio::test]
+    async fn test_session_against_mock_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            // First connection: welcome, a duplicated notification, then a reconnect
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+            socket.send(welcome("first", 10)).await.unwrap();
+            socket.send(ban("message-1")).await.unwrap();
+            socket.send(ban("message-1")).await.unwrap();
+            socket.send(Message::text(json!({
+                "metadata": { "message_id": "reconnect", "message_type": "session_reconnect", "message_timestamp": "2026-10-19T10:00:02Z" },
+                "payload": { "session": { "id": "first", "status": "reconnecting", "reconnect_url": format!("ws://{}/reconnect", addr) } },
+            }).to_string())).await.unwrap();
+
+            // The reconnected session goes quiet past its keepalive timeout
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut reconnected = tokio_tungstenite::accept_async(stream).await.unwrap();
+            reconnected.send(welcome("second", 1)).await.unwrap();
+            reconnected.send(ban("message-2")).await.unwrap();
+
+            // After giving up on the quiet session the client starts a new one
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut fresh = tokio_tungstenite::accept_async(stream).await.unwrap();
+            fresh.send(welcome("third", 10)).await.unwrap();
+            tokio::time::sleep(Duration::from_secs(30)).await;
+            drop((socket, reconnected, fresh));
+        });
+
+        let (events, mut receiver) = mpsc::channel(10);
+        tokio::spawn(run(format!("ws://{}/ws", addr), events));
+
+        let mut received = vec![];
+        while received.len() < 4 {
+            let event = tokio::time::timeout(Duration::from_secs(15), receiver.recv()).await.unwrap().unwrap();
+            received.push(match event {
+                BotEvent::EventSubSession(session_id) => format!("session {}", session_id),
+                BotEvent::Ban(ban) => format!("ban {} by {}", ban.user_login, ban.moderator_user_login),
+                _ => "other".to_string(),
+            });
+        }
+        assert_eq!(received, vec!["session first", "ban spammer by a_mod", "ban spammer by a_mod", "session third"]);
+    }
+}
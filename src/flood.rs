@@ -0,0 +1,218 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::ops::Range;
+use crate::moderation::Action;
+use crate::settings::FloodLimits;
+
+/// Users tracked before idle ones are dropped
+const MAX_TRACKED_USERS: usize = 10_000;
+
+/**
+ * Which flood limit a message broke
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitKind {
+    Rate,
+    Repeats,
+    Length,
+    Caps,
+    Symbols,
+    Emotes,
+}
+
+impl fmt::Display for LimitKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LimitKind::Rate => "message rate",
+            LimitKind::Repeats => "repeats",
+            LimitKind::Length => "length",
+            LimitKind::Caps => "caps",
+            LimitKind::Symbols => "symbols",
+            LimitKind::Emotes => "emotes",
+        };
+        f.write_str(name)
+    }
+}
+
+/**
+ * A broken limit, with the measured value and the limit's maximum
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Violation {
+    pub limit: LimitKind,
+    pub value: f64,
+    pub max: f64,
+    pub action: Action,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.limit {
+            LimitKind::Caps | LimitKind::Symbols => write!(f, "{} {:.0}% > {:.0}%", self.limit, self.value * 100.0, self.max * 100.0),
+            _ => write!(f, "{} {} > {}", self.limit, self.value, self.max),
+        }
+    }
+}
+
+/// A user's recent messages, lowercased with whitespace collapsed
+type History = VecDeque<(DateTime<Utc>, String)>;
+
+/**
+ * Recent messages per channel and user, for the rate and repeat limits
+ */
+#[derive(Debug, Default)]
+pub struct FloodTracker {
+    users: HashMap<(String, String), History>,
+}
+
+impl FloodTracker {
+    /**
+     * Record a message and check it against the channel's limits. Emote ranges
+     * come from the `emotes` tag and are left out of the caps and symbol ratios.
+     */
+    pub fn check(
+        &mut self,
+        channel: &str,
+        user_id: &str,
+        text: &str,
+        emotes: &[Range<usize>],
+        now: DateTime<Utc>,
+        limits: &FloodLimits,
+    ) -> Vec<Violation> {
+        let mut violations = vec![];
+        let mut violation = |limit, value: f64, max: f64, action| {
+            if value > max {
+                violations.push(Violation { limit, value, max, action });
+            }
+        };
+
+        let canonical = text.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+        let window = limits.rate.iter().chain(limits.repeats.iter()).map(|limit| limit.window_secs).max();
+        if let Some(window) = window {
+            if self.users.len() > MAX_TRACKED_USERS {
+                let since = now - Duration::seconds(window.into());
+                self.users.retain(|_, messages| messages.back().is_some_and(|(at, _)| *at > since));
+            }
+
+            let messages = self.users.entry((channel.to_string(), user_id.to_string())).or_default();
+            let since = now - Duration::seconds(window.into());
+            while messages.front().is_some_and(|(at, _)| *at <= since) {
+                messages.pop_front();
+            }
+            messages.push_back((now, canonical.clone()));
+
+            let within = |window_secs: u32| {
+                let since = now - Duration::seconds(window_secs.into());
+                messages.iter().filter(move |(at, _)| *at > since)
+            };
+            if let Some(limit) = &limits.rate {
+                violation(LimitKind::Rate, within(limit.window_secs).count() as f64, limit.max as f64, limit.action);
+            }
+            if let Some(limit) = &limits.repeats {
+                let repeats = within(limit.window_secs).filter(|(_, previous)| *previous == canonical).count();
+                violation(LimitKind::Repeats, repeats as f64, limit.max as f64, limit.action);
+            }
+        }
+
+        if let Some(limit) = &limits.length {
+            violation(LimitKind::Length, text.chars().count() as f64, limit.max as f64, limit.action);
+        }
+        if let Some(limit) = &limits.emotes {
+            violation(LimitKind::Emotes, emotes.len() as f64, limit.max as f64, limit.action);
+        }
+
+        let text = strip_emotes(text, emotes);
+        if let Some(limit) = &limits.caps {
+            let letters: Vec<char> = text.chars().filter(|c| c.is_alphabetic()).collect();
+            if letters.len() >= limit.min_length {
+                let caps = letters.iter().filter(|c| c.is_uppercase()).count();
+                violation(LimitKind::Caps, caps as f64 / letters.len() as f64, limit.max, limit.action);
+            }
+        }
+        if let Some(limit) = &limits.symbols {
+            let chars: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+            if chars.len() >= limit.min_length {
+                let symbols = chars.iter().filter(|c| !c.is_alphanumeric()).count();
+                violation(LimitKind::Symbols, symbols as f64 / chars.len() as f64, limit.max, limit.action);
+            }
+        }
+
+        violations
+    }
+}
+
+/**
+ * Message text without the emotes, ranges are in characters
+ */
+pub fn strip_emotes(text: &str, emotes: &[Range<usize>]) -> String {
+    text.chars()
+        .enumerate()
+        .filter(|(i, _)| !emotes.iter().any(|range| range.contains(i)))
+        .map(|(_, c)| c)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits() -> FloodLimits {
+        serde_json::from_value(serde_json::json!({
+            "rate": { "max": 3, "window_secs": 10 },
+            "repeats": { "max": 1, "window_secs": 30, "action": { "timeout": 60 } },
+            "length": { "max": 40 },
+            "caps": { "max": 0.7, "min_length": 8 },
+            "symbols": { "max": 0.5, "min_length": 8 },
+            "emotes": { "max": 3, "action": "ban" },
+        })).unwrap()
+    }
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000 + seconds, 0).unwrap()
+    }
+
+    fn limits_broken(violations: Vec<Violation>) -> Vec<LimitKind> {
+        violations.into_iter().map(|violation| violation.limit).collect()
+    }
+
+    #[test]
+    fn test_rate_and_repeats() {
+        let mut tracker = FloodTracker::default();
+        let limits = limits();
+
+        assert!(tracker.check("channel", "1", "hello", &[], at(0), &limits).is_empty());
+        assert_eq!(limits_broken(tracker.check("channel", "1", "Hello ", &[], at(1), &limits)), vec![LimitKind::Repeats]);
+        assert!(tracker.check("channel", "1", "how are you", &[], at(2), &limits).is_empty());
+        assert_eq!(limits_broken(tracker.check("channel", "1", "anyone here", &[], at(3), &limits)), vec![LimitKind::Rate]);
+        assert!(tracker.check("channel", "2", "anyone here", &[], at(3), &limits).is_empty());
+        assert!(tracker.check("channel", "1", "ok bye", &[], at(20), &limits).is_empty());
+
+        let violation = &tracker.check("channel", "1", "ok bye", &[], at(21), &limits)[0];
+        assert_eq!(violation.action, Action::Timeout(60));
+        assert_eq!(violation.to_string(), "repeats 2 > 1");
+    }
+
+    #[test]
+    fn test_message_limits() {
+        let mut tracker = FloodTracker::default();
+        let limits = limits();
+        let check = |tracker: &mut FloodTracker, user: &str, text: &str, emotes: &[Range<usize>]| {
+            limits_broken(tracker.check("channel", user, text, emotes, at(0), &limits))
+        };
+
+        assert_eq!(check(&mut tracker, "1", "THIS IS SO LOUD", &[]), vec![LimitKind::Caps]);
+        assert_eq!(check(&mut tracker, "2", "LUL LUL that was funny", &[0..3, 4..7]), vec![]);
+        assert_eq!(check(&mut tracker, "3", "!!!! ???? ####", &[]), vec![LimitKind::Symbols]);
+        assert_eq!(check(&mut tracker, "4", &"a".repeat(41), &[]), vec![LimitKind::Length]);
+        assert_eq!(check(&mut tracker, "5", "Kappa Kappa Kappa Kappa", &[0..5, 6..11, 12..17, 18..23]), vec![LimitKind::Emotes]);
+        assert_eq!(check(&mut tracker, "6", "OK", &[]), vec![]);
+    }
+
+    #[test]
+    fn test_strip_emotes() {
+        assert_eq!(strip_emotes("hi Kappa there Kappa", &[3..8, 15..20]), "hi  there ");
+    }
+}
@@ -0,0 +1,308 @@
+use chrono::{DateTime, Utc};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use twitch_api::client::{BoxedFuture, Bytes, ClientDefault, Request, Response};
+use twitch_api::helix::{
+    ClientRequestError, HelixRequestDeleteError, HelixRequestGetError, HelixRequestPatchError,
+    HelixRequestPostError, HelixRequestPutError,
+};
+use twitch_api::HttpClient;
+
+/**
+ * Rate limit reported by the latest Helix response
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateLimit {
+    pub remaining: Option<u32>,
+    pub reset: Option<DateTime<Utc>>,
+}
+
+impl RateLimit {
+    fn from_headers(remaining: Option<&str>, reset: Option<&str>) -> RateLimit {
+        RateLimit {
+            remaining: remaining.and_then(|remaining| remaining.parse().ok()),
+            reset: reset
+                .and_then(|reset| reset.parse().ok())
+                .and_then(|reset| DateTime::from_timestamp(reset, 0)),
+        }
+    }
+
+    /**
+     * When requests may be sent again, `None` if the bucket is not empty
+     */
+    pub fn exhausted_until(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match (self.remaining, self.reset) {
+            (Some(0), Some(reset)) if reset > now => Some(reset),
+            _ => None,
+        }
+    }
+}
+
+/**
+ * reqwest client for Helix that keeps track of the `Ratelimit-*` response headers
+ */
+#[derive(Clone)]
+pub struct RateLimitedClient {
+    inner: reqwest::Client,
+    /// Answers requests in place of Twitch when set
+    mock: Option<MockHelix>,
+    rate_limit: Arc<Mutex<RateLimit>>,
+}
+
+impl RateLimitedClient {
+    pub fn new() -> RateLimitedClient {
+        RateLimitedClient {
+            inner: <reqwest::Client as ClientDefault>::default_client(),
+            mock: None,
+            rate_limit: Arc::default(),
+        }
+    }
+
+    /**
+     * Client whose requests never leave the process, e.g. for replays
+     */
+    pub fn mock(mock: MockHelix) -> RateLimitedClient {
+        RateLimitedClient { mock: Some(mock), ..RateLimitedClient::new() }
+    }
+
+    pub fn rate_limit(&self) -> RateLimit {
+        *self.rate_limit.lock().unwrap()
+    }
+}
+
+impl HttpClient for RateLimitedClient {
+    type Error = reqwest::Error;
+
+    fn req(&self, request: Request) -> BoxedFuture<'_, Result<Response, Self::Error>> {
+        if let Some(mock) = &self.mock {
+            let response = mock.answer(&request);
+            return Box::pin(async move { Ok(response) });
+        }
+
+        let response = self.inner.req(request);
+        let rate_limit = self.rate_limit.clone();
+        Box::pin(async move {
+            let response = response.await?;
+            let header = |name: &str| response.headers().get(name).and_then(|value| value.to_str().ok());
+            if header("ratelimit-reset").is_some() {
+                *rate_limit.lock().unwrap() = RateLimit::from_headers(header("ratelimit-remaining"), header("ratelimit-reset"));
+            }
+            Ok(response)
+        })
+    }
+}
+
+/**
+ * A request made to a mock Helix
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HelixCall {
+    pub method: String,
+    /// Path under `/helix`, e.g. `/moderation/bans`
+    pub path: String,
+    pub query: String,
+    pub body: String,
+}
+
+impl HelixCall {
+    fn body_json(&self) -> serde_json::Value {
+        serde_json::from_str(&self.body).unwrap_or_default()
+    }
+
+    /**
+     * What the call does, e.g. `ban 123` or `timeout 123 for 60s`, the
+     * request itself for calls other than bans and deletions
+     */
+    pub fn describe(&self) -> String {
+        let body = self.body_json();
+        let user = body["data"]["user_id"].as_str().unwrap_or_default();
+        match (self.method.as_str(), self.path.as_str()) {
+            ("POST", "/moderation/bans") => match body["data"]["duration"].as_u64() {
+                Some(duration) => format!("timeout {} for {}s", user, duration),
+                None => format!("ban {}", user),
+            },
+            ("DELETE", "/moderation/chat") => {
+                let message_id = self.query.split('&').find_map(|pair| pair.strip_prefix("message_id="));
+                format!("delete message {}", message_id.unwrap_or_default())
+            },
+            _ if self.query.is_empty() => format!("{} {} {}", self.method, self.path, self.body).trim_end().to_string(),
+            _ => format!("{} {}?{} {}", self.method, self.path, self.query, self.body).trim_end().to_string(),
+        }
+    }
+}
+
+/// Answer to bans on a mock Helix without a response of its own
+const MOCK_BAN: &str = r#"{"data":[{"broadcaster_id":"0","created_at":"1970-01-01T00:00:00Z","end_time":null,"moderator_id":"0","user_id":"0"}]}"#;
+
+/**
+ * Helix stand-in that keeps the requests made and lets them all succeed:
+ * bans are confirmed, deletions and held message reviews answered with 204
+ * and everything else with an empty list
+ */
+#[derive(Debug, Clone, Default)]
+pub struct MockHelix {
+    calls: Arc<Mutex<Vec<HelixCall>>>,
+}
+
+impl MockHelix {
+    /**
+     * Requests made since the last call
+     */
+    pub fn take_calls(&self) -> Vec<HelixCall> {
+        std::mem::take(&mut *self.calls.lock().unwrap())
+    }
+
+    fn answer(&self, request: &Request) -> Response {
+        let call = HelixCall {
+            method: request.method().as_str().to_string(),
+            path: request.uri().path().trim_start_matches("/helix").to_string(),
+            query: request.uri().query().unwrap_or_default().to_string(),
+            body: String::from_utf8_lossy(request.body()).to_string(),
+        };
+
+        let (status, body) = match (call.method.as_str(), call.path.as_str()) {
+            ("POST", "/moderation/bans") => (200, MOCK_BAN),
+            ("DELETE", _) | ("POST", "/moderation/automod/message") => (204, ""),
+            _ => (200, r#"{"data":[]}"#),
+        };
+        self.calls.lock().unwrap().push(call);
+
+        let mut response = Response::new(Bytes::from(body));
+        *response.status_mut() = status.try_into().unwrap_or_default();
+        response
+    }
+}
+
+/**
+ * Failed Helix call, with enough detail to decide whether to retry it
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HelixError {
+    pub message: String,
+    pub status: Option<u16>,
+    /// Rate limited, retry no earlier than this
+    pub retry_at: Option<DateTime<Utc>>,
+    /// Retrying cannot help, e.g. the bot's user ID is unknown
+    pub permanent: bool,
+}
+
+impl HelixError {
+    /**
+     * Error for a call that could not be made at all, e.g. before the client is initialized
+     */
+    pub fn unavailable(message: &str) -> HelixError {
+        HelixError {
+            message: message.to_string(),
+            status: None,
+            retry_at: None,
+            permanent: false,
+        }
+    }
+
+    /**
+     * Error for a call that cannot succeed until the bot is reconfigured
+     */
+    pub fn permanent(message: &str) -> HelixError {
+        HelixError {
+            permanent: true,
+            ..HelixError::unavailable(message)
+        }
+    }
+
+    pub fn from_client_error<E: std::error::Error + Send + Sync + 'static>(e: &ClientRequestError<E>, rate_limit: RateLimit) -> HelixError {
+        let status = match e {
+            ClientRequestError::HelixRequestGetError(HelixRequestGetError::Error { status, .. })
+            | ClientRequestError::HelixRequestPutError(HelixRequestPutError::Error { status, .. })
+            | ClientRequestError::HelixRequestPostError(HelixRequestPostError::Error { status, .. })
+            | ClientRequestError::HelixRequestPatchError(HelixRequestPatchError::Error { status, .. })
+            | ClientRequestError::HelixRequestDeleteError(HelixRequestDeleteError::Error { status, .. }) => Some(status.as_u16()),
+            _ => None,
+        };
+
+        HelixError {
+            message: e.to_string(),
+            status,
+            retry_at: if status == Some(429) { rate_limit.reset } else { None },
+            permanent: false,
+        }
+    }
+
+    /**
+     * Network errors, rate limits and server errors are worth retrying. A
+     * rejected token is not, the bot's user token cannot be refreshed.
+     */
+    pub fn is_retryable(&self) -> bool {
+        if self.permanent {
+            return false;
+        }
+        match self.status {
+            None => true,
+            Some(status) => status == 429 || status >= 500,
+        }
+    }
+
+    /**
+     * The bot cannot moderate until its token or user ID is fixed
+     */
+    pub fn needs_credentials(&self) -> bool {
+        self.permanent || self.status == Some(401)
+    }
+}
+
+impl fmt::Display for HelixError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.status {
+            Some(status) => write!(f, "{} ({})", self.message, status),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limit() {
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let limit = RateLimit::from_headers(Some("0"), Some("1700000030"));
+        assert_eq!(limit.exhausted_until(now), Some(now + chrono::Duration::seconds(30)));
+        assert_eq!(RateLimit::from_headers(Some("799"), Some("1700000030")).exhausted_until(now), None);
+        assert_eq!(RateLimit::from_headers(None, Some("soon")), RateLimit { remaining: None, reset: None });
+    }
+
+    #[test]
+    fn test_retryable() {
+        let error = |status| HelixError { message: "error".to_string(), status, retry_at: None, permanent: false };
+        assert!(error(Some(429)).is_retryable());
+        assert!(error(Some(503)).is_retryable());
+        assert!(error(None).is_retryable());
+        assert!(!error(Some(400)).is_retryable());
+        assert!(!error(Some(401)).is_retryable() && error(Some(401)).needs_credentials());
+        assert!(!HelixError::permanent("Bot user ID unknown").is_retryable());
+        assert_eq!(error(Some(400)).to_string(), "error (400)");
+    }
+
+    #[tokio::test]
+    async fn test_mock_helix() {
+        let request = |method: &str, uri: &str, body: &str| {
+            let mut request = Request::new(Bytes::from(body.to_string()));
+            *request.method_mut() = method.parse().unwrap();
+            *request.uri_mut() = uri.parse().unwrap();
+            request
+        };
+        let mock = MockHelix::default();
+        let client = RateLimitedClient::mock(mock.clone());
+
+        let ban = r#"{"data":{"user_id":"42","duration":60,"reason":"spam"}}"#;
+        let response = client.req(request("POST", "https://api.twitch.tv/helix/moderation/bans?broadcaster_id=1", ban)).await.unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+        let response = client.req(request("DELETE", "https://api.twitch.tv/helix/moderation/chat?broadcaster_id=1&message_id=abc", "")).await.unwrap();
+        assert_eq!(response.status().as_u16(), 204);
+        client.req(request("GET", "https://api.twitch.tv/helix/chat/settings?broadcaster_id=1", "")).await.unwrap();
+
+        let calls: Vec<String> = mock.take_calls().iter().map(HelixCall::describe).collect();
+        assert_eq!(calls, vec!["timeout 42 for 60s", "delete message abc", "GET /chat/settings?broadcaster_id=1"]);
+        assert!(mock.take_calls().is_empty());
+    }
+}
@@ -0,0 +1,270 @@
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+
+/// Messages before a mod action that count as evidence
+pub const EVIDENCE_MINUTES: i64 = 10;
+
+/// Latest messages recorded per actioned user
+pub const MESSAGES_PER_USER: usize = 5;
+
+/// How long recorded messages are learned from
+const SAMPLE_DAYS: i64 = 7;
+
+/// Distinct actioned users a phrase or URL needs to be suggested
+const MIN_USERS: usize = 3;
+
+/// Other chatters allowed to have used a candidate, more and it is ordinary chat
+const MAX_INNOCENT_USERS: usize = 1;
+
+/// Words per phrase candidate
+const PHRASE_WORDS: std::ops::RangeInclusive<usize> = 2..=4;
+
+/// Shorter phrases are too likely to be ordinary chat
+const MIN_PHRASE_CHARS: usize = 8;
+
+/// Suggestions listed by `!suggestions`
+pub const MAX_SUGGESTIONS: usize = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Candidate {
+    Phrase(String),
+    /// Domain, without scheme, `www.` or path
+    Url(String),
+}
+
+impl fmt::Display for Candidate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Candidate::Phrase(phrase) => write!(f, "phrase \"{}\"", phrase),
+            Candidate::Url(url) => write!(f, "URL {}", url),
+        }
+    }
+}
+
+/**
+ * A candidate and how many actioned users posted it
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    pub candidate: Candidate,
+    pub users: usize,
+}
+
+/**
+ * Messages of a user a human moderator banned, timed out or deleted
+ */
+#[derive(Debug, Clone)]
+struct Sample {
+    at: DateTime<Utc>,
+    channel: String,
+    login: String,
+    candidates: HashSet<Candidate>,
+}
+
+/**
+ * Phrases and URLs that keep showing up in messages human moderators act on
+ */
+#[derive(Debug, Default)]
+pub struct Learner {
+    samples: VecDeque<Sample>,
+    dismissed: HashSet<Candidate>,
+    announced: HashSet<(String, Candidate)>,
+}
+
+impl Learner {
+    /**
+     * Record the messages of a user a moderator acted on. A user recorded in
+     * the channel within the evidence window, e.g. for a CLEARCHAT and the
+     * EventSub ban of the same ban, is only recorded once.
+     */
+    pub fn record(&mut self, channel: &str, login: &str, texts: &[&str], now: DateTime<Utc>) -> bool {
+        let since = now - Duration::days(SAMPLE_DAYS);
+        while self.samples.front().is_some_and(|sample| sample.at <= since) {
+            self.samples.pop_front();
+        }
+
+        let candidates: HashSet<Candidate> = texts.iter().flat_map(|text| candidates(text)).collect();
+        let recently = now - Duration::minutes(EVIDENCE_MINUTES);
+        if let Some(sample) = self.samples.iter_mut().find(|sample| sample.channel == channel && sample.login == login && sample.at > recently) {
+            sample.candidates.extend(candidates);
+            return false;
+        }
+        if candidates.is_empty() {
+            return false;
+        }
+
+        self.samples.push_back(Sample {
+            at: now,
+            channel: channel.to_string(),
+            login: login.to_string(),
+            candidates,
+        });
+        true
+    }
+
+    /**
+     * Candidates posted by enough actioned users in the channel, most users
+     * first. `innocent` are messages of other chatters with their logins,
+     * candidates they use are ordinary chat. `known` tells which candidates
+     * are banned already.
+     */
+    pub fn suggestions<'a>(&self, channel: &str, innocent: impl Iterator<Item = (&'a str, &'a str)>, known: impl Fn(&Candidate) -> bool) -> Vec<Suggestion> {
+        let mut users: HashMap<&Candidate, HashSet<&str>> = HashMap::new();
+        for sample in self.samples.iter().filter(|sample| sample.channel == channel) {
+            for candidate in &sample.candidates {
+                users.entry(candidate).or_default().insert(sample.login.as_str());
+            }
+        }
+        users.retain(|candidate, logins| {
+            logins.len() >= MIN_USERS
+                && !self.dismissed.iter().any(|dismissed| within(candidate, dismissed))
+                && !known(candidate)
+        });
+        if users.is_empty() {
+            return vec![];
+        }
+
+        let mut innocent_users: HashMap<&Candidate, HashSet<&str>> = HashMap::new();
+        for (login, text) in innocent {
+            for candidate in candidates(text) {
+                if let Some((candidate, logins)) = users.get_key_value(&candidate) {
+                    if !logins.contains(login) {
+                        innocent_users.entry(*candidate).or_default().insert(login);
+                    }
+                }
+            }
+        }
+
+        let mut suggestions: Vec<Suggestion> = users.iter()
+            .filter(|(candidate, _)| innocent_users.get(*candidate).is_none_or(|logins| logins.len() <= MAX_INNOCENT_USERS))
+            .map(|(candidate, logins)| Suggestion { candidate: (*candidate).clone(), users: logins.len() })
+            .collect();
+
+        // A phrase inside a longer one posted by as many users adds nothing
+        let longer = suggestions.clone();
+        suggestions.retain(|suggestion| {
+            let Candidate::Phrase(phrase) = &suggestion.candidate else {
+                return true;
+            };
+            !longer.iter().any(|other| other.users >= suggestion.users && matches!(&other.candidate, Candidate::Phrase(other_phrase) if other_phrase.len() > phrase.len()) && within(&suggestion.candidate, &other.candidate))
+        });
+
+        suggestions.sort_by(|a, b| b.users.cmp(&a.users).then_with(|| a.candidate.cmp(&b.candidate)));
+        suggestions
+    }
+
+    /**
+     * Whether a suggestion is new in the channel, so that it is announced once
+     */
+    pub fn announce(&mut self, channel: &str, candidate: &Candidate) -> bool {
+        self.announced.insert((channel.to_string(), candidate.clone()))
+    }
+
+    /**
+     * Never suggest a candidate again, nor the phrases within it, e.g. after a
+     * mod approved or rejected it
+     */
+    pub fn dismiss(&mut self, candidate: Candidate) {
+        self.dismissed.insert(candidate);
+    }
+}
+
+/**
+ * Whether a candidate is the other one or a phrase within it
+ */
+fn within(candidate: &Candidate, other: &Candidate) -> bool {
+    match (candidate, other) {
+        (Candidate::Phrase(phrase), Candidate::Phrase(other)) => format!(" {} ", other).contains(&format!(" {} ", phrase)),
+        _ => candidate == other,
+    }
+}
+
+/**
+ * URLs and word n-grams of a message
+ */
+pub fn candidates(text: &str) -> HashSet<Candidate> {
+    let mut candidates = HashSet::new();
+    let mut words = vec![];
+    for token in text.split_whitespace() {
+        if let Some(domain) = domain(token) {
+            candidates.insert(Candidate::Url(domain));
+            continue;
+        }
+        // Kept readable for mods, banned words are normalized when matched anyway
+        let word: String = token.to_lowercase().chars().filter(|c| c.is_alphanumeric()).collect();
+        if !word.is_empty() {
+            words.push(word);
+        }
+    }
+
+    for n in PHRASE_WORDS {
+        for window in words.windows(n) {
+            let phrase = window.join(" ");
+            if phrase.chars().count() >= MIN_PHRASE_CHARS {
+                candidates.insert(Candidate::Phrase(phrase));
+            }
+        }
+    }
+    candidates
+}
+
+/**
+ * Domain of a token that looks like a URL, e.g. `https://www.Example.com/x` or `example.com`
+ */
+fn domain(token: &str) -> Option<String> {
+    let token = token.trim_matches(|c: char| !c.is_alphanumeric());
+    let lower = token.to_lowercase();
+    let rest = lower.strip_prefix("https://").or_else(|| lower.strip_prefix("http://")).unwrap_or(&lower);
+    let host = rest.split(['/', '?', '#']).next()?;
+    let host = host.strip_prefix("www.").unwrap_or(host);
+
+    let labels: Vec<&str> = host.split('.').collect();
+    let tld = labels.last()?;
+    let valid = labels.len() >= 2
+        && labels.iter().all(|label| !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'))
+        && tld.len() >= 2
+        && tld.chars().all(|c| c.is_ascii_alphabetic());
+    valid.then(|| host.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candidates() {
+        let candidates = candidates("Get CHEAP viewers at https://www.BigFollows.com/now ok.");
+        assert!(candidates.contains(&Candidate::Url("bigfollows.com".to_string())));
+        assert!(candidates.contains(&Candidate::Phrase("cheap viewers".to_string())));
+        assert!(candidates.contains(&Candidate::Phrase("get cheap viewers at".to_string())));
+        assert!(!candidates.contains(&Candidate::Phrase("at ok".to_string())));
+        assert!(!candidates.iter().any(|candidate| matches!(candidate, Candidate::Url(url) if url == "ok")));
+    }
+
+    #[test]
+    fn test_suggestions() {
+        let now = Utc::now();
+        let mut learner = Learner::default();
+        assert!(learner.record("channel", "bot1", &["wanna become famous? bigfollows.com"], now));
+        assert!(learner.record("channel", "bot2", &["hey wanna become famous bigfollows . com"], now));
+        assert!(!learner.record("channel", "bot2", &["bigfollows.com"], now));
+        assert!(learner.record("channel", "bot3", &["Wanna become famous? visit bigfollows.com", "hello streamer"], now));
+        assert!(learner.record("other", "bot4", &["wanna become famous?"], now));
+
+        let innocent = [("viewer", "i wanna become a streamer"), ("viewer2", "hello streamer"), ("viewer3", "hello streamer!")];
+        let suggestions = learner.suggestions("channel", innocent.iter().copied(), |_| false);
+        assert_eq!(suggestions, vec![
+            Suggestion { candidate: Candidate::Phrase("wanna become famous".to_string()), users: 3 },
+            Suggestion { candidate: Candidate::Url("bigfollows.com".to_string()), users: 3 },
+        ]);
+
+        let known = |candidate: &Candidate| matches!(candidate, Candidate::Url(_));
+        assert_eq!(learner.suggestions("channel", [].into_iter(), known).len(), 1);
+
+        assert!(learner.announce("channel", &suggestions[0].candidate));
+        assert!(!learner.announce("channel", &suggestions[0].candidate));
+        learner.dismiss(suggestions[0].candidate.clone());
+        assert_eq!(learner.suggestions("channel", [].into_iter(), known), vec![]);
+    }
+}
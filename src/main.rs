@@ -1,13 +1,37 @@
 use bot::Bot;
 use dotenvy::dotenv;
-use opts::Opts;
+use opts::{Cli, Command, Opts};
 use clap::Parser;
 use color_eyre::Result;
-use tracing_subscriber;
 
 pub mod opts;
+mod admin;
+mod automod;
+mod badges;
+mod blocked_terms;
+mod botlists;
+mod breaker;
 mod bot;
+mod check;
+mod commands;
 mod errors;
+mod eventsub;
+mod flood;
+mod helix;
+mod learning;
+mod matcher;
+mod moderation;
+mod normalize;
+mod nuke;
+mod queue;
+mod raid;
+mod recent;
+mod replay;
+mod settings;
+mod sweep;
+mod usernames;
+mod waves;
+mod webhook;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>>  {
@@ -18,13 +42,36 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>
             tracing_subscriber::EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| "twitchbot=debug".into()),
         )
+        // Keeps stdout to what subcommands print, like the check report
+        .with_writer(std::io::stderr)
         .init();
     color_eyre::install().unwrap();
-    dotenv().expect(".env file not found");
-    let args = Opts::parse();
+    let env_file = dotenv();
+    let cli = Cli::parse();
+    // Checking a log or replaying a session needs no credentials, so no .env either
+    if !matches!(cli.command, Some(Command::Check(_) | Command::Replay(_))) {
+        env_file.expect(".env file not found");
+    }
 
-    let mut bot = Bot::new(args);
-    let _res = bot.run().await;
+    match cli.command {
+        Some(Command::ImportBots { opts, lists }) => Bot::new(opts).import_bots(lists).await?,
+        Some(Command::Check(opts)) => {
+            let report = Bot::offline(opts.database_url.clone()).check(&opts).await?;
+            print!("{}", report.render(opts.all));
+        },
+        Some(Command::Replay(opts)) => {
+            let transcript = Bot::offline(None).replay_session(&opts).await?;
+            print!("{}", transcript.render());
+        },
+        Some(Command::Run(opts)) => {
+            let _res = Bot::new(opts).run().await;
+        },
+        None => {
+            // Without any flags or environment the options are missing, parsing again reports which
+            let opts = cli.opts.unwrap_or_else(Opts::from_env);
+            let _res = Bot::new(opts).run().await;
+        },
+    }
 
     Ok(())
 }
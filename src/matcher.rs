@@ -0,0 +1,566 @@
+use aho_corasick::AhoCorasick;
+use chrono::{DateTime, Utc};
+use entity::banned_words;
+use entity::sea_orm_active_enums::{MatchMode, RuleAction};
+use regex::{Regex, RegexBuilder, RegexSetBuilder};
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use crate::normalize::{normalize, NormalizeOptions, NormalizeOverrides};
+
+/// Compiled size limit for a single banned regex
+pub const REGEX_SIZE_LIMIT: usize = 256 * 1024;
+
+/// Time a single message may spend in the matcher before the remaining regex sets are skipped
+pub const MATCH_BUDGET: Duration = Duration::from_millis(10);
+
+/// Regexes compiled into one set, the matching budget is checked between sets
+const REGEX_SET_SIZE: usize = 32;
+
+/// Ordinary chat lines, a pattern matching all of them would action everyone
+const PROBES: [&str; 6] = ["a", "hello", "Hello chat!", "1234", " ", "😀"];
+
+/**
+ * Why a banned word was not accepted
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleError {
+    InvalidRegex(String),
+    TooComplex,
+    MatchesEmpty,
+    MatchesEverything,
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleError::InvalidRegex(e) => write!(f, "invalid regex: {}", e),
+            RuleError::TooComplex => write!(f, "regex is too complex (over {} bytes compiled)", REGEX_SIZE_LIMIT),
+            RuleError::MatchesEmpty => f.write_str("pattern matches the empty string"),
+            RuleError::MatchesEverything => f.write_str("pattern matches every message"),
+        }
+    }
+}
+
+pub fn compile_regex(pattern: &str) -> Result<Regex, RuleError> {
+    RegexBuilder::new(pattern)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .build()
+        .map_err(|e| match e {
+            regex::Error::CompiledTooBig(_) => RuleError::TooComplex,
+            e => RuleError::InvalidRegex(e.to_string()),
+        })
+}
+
+fn compile_regex_set(patterns: &[String], size_limit: usize) -> Result<regex::RegexSet, RuleError> {
+    RegexSetBuilder::new(patterns)
+        .size_limit(size_limit)
+        .build()
+        .map_err(|e| match e {
+            regex::Error::CompiledTooBig(_) => RuleError::TooComplex,
+            e => RuleError::InvalidRegex(e.to_string()),
+        })
+}
+
+/**
+ * A banned word or regex as loaded from the database
+ */
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub id: i32,
+    pub pattern: String,
+    pub mode: MatchMode,
+    pub normalize: NormalizeOptions,
+    pub severity: i32,
+    pub action: Option<RuleAction>,
+    pub timeout_duration: Option<u32>,
+    pub author: Option<String>,
+    pub note: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Canary rules are only ever enforced in shadow mode
+    pub canary: bool,
+    /// Login of the channel the rule is limited to, `None` for all channels
+    pub channel: Option<String>,
+}
+
+impl Rule {
+    /**
+     * Rule with default metadata, normalization defaults follow the match mode
+     */
+    pub fn new(id: i32, pattern: &str, mode: MatchMode) -> Rule {
+        Rule {
+            id,
+            pattern: pattern.to_string(),
+            mode,
+            normalize: NormalizeOptions::defaults_for(mode == MatchMode::Regex),
+            severity: 1,
+            action: None,
+            timeout_duration: None,
+            author: None,
+            note: None,
+            expires_at: None,
+            canary: false,
+            channel: None,
+        }
+    }
+
+    /**
+     * Rule from its row, `channels` maps channel IDs to logins
+     */
+    pub fn from_model(bw: banned_words::Model, channels: &HashMap<i32, String>) -> Rule {
+        // `is_regex` predates `match_mode` and still wins when set
+        let mode = if bw.is_regex { MatchMode::Regex } else { bw.match_mode };
+        let overrides = NormalizeOverrides::from_json(&bw.normalization).unwrap_or_else(|e| {
+            warn!("Invalid normalization for banned word {}: {}", bw.word, e);
+            NormalizeOverrides::default()
+        });
+
+        Rule {
+            id: bw.id,
+            normalize: overrides.resolve(mode == MatchMode::Regex),
+            pattern: bw.word,
+            mode,
+            severity: bw.severity,
+            action: bw.action,
+            timeout_duration: bw.timeout_duration.and_then(|duration| u32::try_from(duration).ok()),
+            author: bw.author,
+            note: bw.note,
+            expires_at: bw.expires_at.map(|expires_at| expires_at.with_timezone(&Utc)),
+            canary: bw.canary,
+            channel: bw.channel_id.and_then(|id| channels.get(&id).cloned()),
+        }
+    }
+
+    pub fn applies_to(&self, channel: &str) -> bool {
+        self.channel.as_deref().is_none_or(|rule_channel| rule_channel == channel)
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+
+    /**
+     * Pattern as it goes into the regex set, `None` for plain words
+     */
+    fn regex_pattern(&self) -> Option<String> {
+        match self.mode {
+            MatchMode::Regex => Some(self.pattern.clone()),
+            MatchMode::WholeWord => Some(whole_word_pattern(&normalize(&self.pattern, self.normalize))),
+            MatchMode::Substring | MatchMode::CaseInsensitive => None,
+        }
+    }
+
+    /**
+     * Pattern as it goes into an automaton
+     */
+    fn word_pattern(&self) -> String {
+        // Case is folded before normalization, confusable skeletons are case sensitive
+        if self.mode == MatchMode::CaseInsensitive {
+            normalize(&self.pattern.to_lowercase(), self.normalize)
+        } else {
+            normalize(&self.pattern, self.normalize)
+        }
+    }
+
+    /**
+     * Check that the rule compiles within the size limit and does not match
+     * the empty string or every message. Used on every path that adds rules.
+     */
+    pub fn validate(&self) -> Result<(), RuleError> {
+        if self.mode != MatchMode::Regex && self.word_pattern().trim().is_empty() {
+            return Err(RuleError::MatchesEmpty);
+        }
+
+        if let Some(pattern) = self.regex_pattern() {
+            let regex = compile_regex(&pattern)?;
+            if regex.is_match("") {
+                return Err(RuleError::MatchesEmpty);
+            }
+            if PROBES.iter().all(|probe| regex.is_match(probe)) {
+                return Err(RuleError::MatchesEverything);
+            }
+        }
+        Ok(())
+    }
+}
+
+/**
+ * A rule that matched and the part of the normalized message it matched on
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuleMatch {
+    pub rule_id: i32,
+    pub pattern: String,
+    pub matched: String,
+}
+
+/**
+ * Plain words compiled into one automaton
+ */
+#[derive(Debug)]
+struct WordSet {
+    automaton: AhoCorasick,
+    ids: Vec<i32>,
+    lowercase: bool,
+}
+
+/**
+ * Regexes compiled into one set
+ */
+#[derive(Debug)]
+struct RegexChunk {
+    set: regex::RegexSet,
+    ids: Vec<i32>,
+}
+
+/**
+ * Rules sharing the same normalization options, compiled together
+ */
+#[derive(Debug)]
+struct MatcherGroup {
+    normalize: NormalizeOptions,
+    words: Vec<WordSet>,
+    regexes: Vec<RegexChunk>,
+}
+
+/**
+ * Rules a message matched
+ */
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Found {
+    /// Matching rule IDs, sorted and deduplicated
+    pub ids: Vec<i32>,
+    /// Regex sets left unchecked because the matching budget was spent
+    pub skipped: usize,
+}
+
+/**
+ * Compiled matcher for all banned words: plain words go into Aho-Corasick
+ * automata and regexes into `RegexSet`s of up to [`REGEX_SET_SIZE`] rules,
+ * per normalization group
+ */
+#[derive(Debug, Default)]
+pub struct Matcher {
+    groups: Vec<MatcherGroup>,
+    rules: HashMap<i32, Rule>,
+}
+
+impl Matcher {
+    /**
+     * Compile the rules. Rules that fail to compile are returned with the error
+     * instead of being part of the matcher.
+     */
+    pub fn build(rules: Vec<Rule>) -> (Matcher, Vec<(Rule, RuleError)>) {
+        let mut grouped: HashMap<NormalizeOptions, Vec<Rule>> = HashMap::new();
+        let mut rejected = vec![];
+        for rule in rules {
+            match rule.validate() {
+                Ok(()) => grouped.entry(rule.normalize).or_default().push(rule),
+                Err(e) => rejected.push((rule, e)),
+            }
+        }
+
+        let mut matcher = Matcher::default();
+
+        for (normalize_options, rules) in grouped {
+            let mut regex_rules = vec![];
+            let mut substrings = vec![];
+            let mut case_insensitive = vec![];
+
+            for rule in rules {
+                match rule.mode {
+                    MatchMode::Substring => substrings.push(rule),
+                    MatchMode::CaseInsensitive => case_insensitive.push(rule),
+                    MatchMode::WholeWord | MatchMode::Regex => regex_rules.push(rule),
+                }
+            }
+
+            let mut words = vec![];
+            for (rules, lowercase) in [(substrings, false), (case_insensitive, true)] {
+                if rules.is_empty() {
+                    continue;
+                }
+
+                let patterns: Vec<String> = rules.iter().map(Rule::word_pattern).collect();
+                match AhoCorasick::new(&patterns) {
+                    Ok(automaton) => {
+                        words.push(WordSet {
+                            automaton,
+                            ids: rules.iter().map(|rule| rule.id).collect(),
+                            lowercase,
+                        });
+                        matcher.rules.extend(rules.into_iter().map(|rule| (rule.id, rule)));
+                    },
+                    Err(e) => rejected.extend(rules.into_iter().map(|rule| (rule, RuleError::InvalidRegex(e.to_string())))),
+                }
+            }
+
+            let mut regexes = vec![];
+            for chunk in regex_rules.chunks(REGEX_SET_SIZE) {
+                // Every pattern passed validation on its own, so the set should fit in their combined limit
+                let patterns: Vec<String> = chunk.iter().map(|rule| rule.regex_pattern().unwrap_or_default()).collect();
+                match compile_regex_set(&patterns, REGEX_SIZE_LIMIT.saturating_mul(patterns.len())) {
+                    Ok(set) => {
+                        regexes.push(RegexChunk { set, ids: chunk.iter().map(|rule| rule.id).collect() });
+                        matcher.rules.extend(chunk.iter().map(|rule| (rule.id, rule.clone())));
+                    },
+                    Err(e) => rejected.extend(chunk.iter().map(|rule| (rule.clone(), e.clone()))),
+                }
+            }
+
+            matcher.groups.push(MatcherGroup {
+                normalize: normalize_options,
+                words,
+                regexes,
+            });
+        }
+
+        (matcher, rejected)
+    }
+
+    /**
+     * Number of compiled rules
+     */
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn get(&self, id: i32) -> Option<&Rule> {
+        self.rules.get(&id)
+    }
+
+    /**
+     * IDs of all unexpired rules matching the text, sorted and deduplicated
+     */
+    pub fn find(&self, text: &str, now: DateTime<Utc>) -> Vec<i32> {
+        self.find_within(text, now, MATCH_BUDGET).ids
+    }
+
+    /**
+     * Like [`Matcher::find`], leaving out rules limited to other channels
+     */
+    pub fn find_in(&self, channel: &str, text: &str, now: DateTime<Utc>) -> Found {
+        let mut found = self.find_within(text, now, MATCH_BUDGET);
+        found.ids.retain(|id| self.rules[id].applies_to(channel));
+        found
+    }
+
+    /**
+     * Like [`Matcher::find`], but skips the remaining regex sets once the budget
+     * is spent. Plain words are cheap and always checked in every group.
+     */
+    pub fn find_within(&self, text: &str, now: DateTime<Utc>, budget: Duration) -> Found {
+        let started = Instant::now();
+        let mut found = Found::default();
+        let mut normalized = Vec::with_capacity(self.groups.len());
+
+        for group in &self.groups {
+            let text_normalized = normalize(text, group.normalize);
+            let mut lowercased = None;
+
+            for words in &group.words {
+                let text = if words.lowercase {
+                    lowercased.get_or_insert_with(|| normalize(&text.to_lowercase(), group.normalize))
+                } else {
+                    &text_normalized
+                };
+                found.ids.extend(words.automaton.find_overlapping_iter(text.as_str()).map(|m| words.ids[m.pattern().as_usize()]));
+            }
+            normalized.push(text_normalized);
+        }
+
+        for (group, text) in self.groups.iter().zip(&normalized) {
+            for chunk in &group.regexes {
+                if started.elapsed() > budget {
+                    found.skipped += 1;
+                    continue;
+                }
+                found.ids.extend(chunk.set.matches(text).into_iter().map(|i| chunk.ids[i]));
+            }
+        }
+        if found.skipped > 0 {
+            warn!("Matching budget of {:?} exceeded, skipped {} regex sets", budget, found.skipped);
+        }
+
+        found.ids.sort_unstable();
+        found.ids.dedup();
+        found.ids.retain(|id| !self.rules[id].is_expired(now));
+        found
+    }
+}
+
+impl Matcher {
+    /**
+     * Find what each of the given rules matched on. Only used to explain a
+     * decision, so rules are checked one by one.
+     */
+    pub fn explain(&self, text: &str, ids: &[i32]) -> Vec<RuleMatch> {
+        ids.iter().filter_map(|id| {
+            let rule = self.rules.get(id)?;
+            let text = if rule.mode == MatchMode::CaseInsensitive {
+                normalize(&text.to_lowercase(), rule.normalize)
+            } else {
+                normalize(text, rule.normalize)
+            };
+
+            let matched = match rule.regex_pattern() {
+                Some(pattern) => compile_regex(&pattern).ok()?.find(&text)?.as_str().trim().to_string(),
+                None => {
+                    let word = rule.word_pattern();
+                    text.contains(&word).then_some(word)?
+                },
+            };
+
+            Some(RuleMatch {
+                rule_id: rule.id,
+                pattern: rule.pattern.clone(),
+                matched,
+            })
+        }).collect()
+    }
+}
+
+/**
+ * Regex matching a literal only when it is not part of a longer word
+ */
+fn whole_word_pattern(word: &str) -> String {
+    format!(r"(?:^|\W){}(?:$|\W)", regex::escape(word))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(id: i32, pattern: &str, mode: MatchMode) -> Rule {
+        Rule::new(id, pattern, mode)
+    }
+
+    #[test]
+    fn test_find_reports_rule_ids() {
+        let (matcher, rejected) = Matcher::build(vec![
+            rule(1, "cheap viewers", MatchMode::Substring),
+            rule(2, "viewers", MatchMode::Substring),
+            rule(3, r"buy\s+followers", MatchMode::Regex),
+            rule(4, "(unclosed", MatchMode::Regex),
+        ]);
+        assert_eq!(matcher.len(), 3);
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].0.id, 4);
+        assert!(matches!(rejected[0].1, RuleError::InvalidRegex(_)));
+
+        assert_eq!(matcher.find("ⓒheap v1ewers and buy   followers", Utc::now()), vec![1, 2, 3]);
+        assert_eq!(matcher.find("viewers", Utc::now()), vec![2]);
+        assert!(matcher.find("hello chat", Utc::now()).is_empty());
+    }
+
+    #[test]
+    fn test_large_rule_list() {
+        let rules: Vec<Rule> = (0..5000).map(|i| rule(i, &format!("spamword{}x", i), MatchMode::Substring)).collect();
+        let (matcher, rejected) = Matcher::build(rules);
+        assert!(rejected.is_empty());
+        assert_eq!(matcher.find("please buy spamword4242x now", Utc::now()), vec![4242]);
+    }
+
+    #[test]
+    fn test_match_modes() {
+        let (matcher, rejected) = Matcher::build(vec![
+            rule(1, "Promo", MatchMode::Substring),
+            rule(2, "promo", MatchMode::CaseInsensitive),
+            rule(3, "bot", MatchMode::WholeWord),
+        ]);
+        assert!(rejected.is_empty());
+
+        assert_eq!(matcher.find("PROMO code", Utc::now()), vec![2]);
+        assert_eq!(matcher.find("Promo code", Utc::now()), vec![1, 2]);
+        assert_eq!(matcher.find("you are a bot!", Utc::now()), vec![3]);
+        assert!(matcher.find("robots", Utc::now()).is_empty());
+    }
+
+    #[test]
+    fn test_expired_rules_are_skipped() {
+        let now = Utc::now();
+        let mut expired = rule(1, "drama", MatchMode::Substring);
+        expired.expires_at = Some(now - chrono::Duration::minutes(1));
+        let mut active = rule(2, "drama", MatchMode::Substring);
+        active.expires_at = Some(now + chrono::Duration::minutes(1));
+
+        let (matcher, _) = Matcher::build(vec![expired, active]);
+        assert_eq!(matcher.find("more drama", now), vec![2]);
+        assert!(matcher.find("more drama", now + chrono::Duration::minutes(2)).is_empty());
+    }
+
+    #[test]
+    fn test_channel_rules() {
+        let now = Utc::now();
+        let scoped = Rule { channel: Some("a".to_string()), ..rule(1, "drama", MatchMode::Substring) };
+        let (matcher, _) = Matcher::build(vec![scoped, rule(2, "drama", MatchMode::Substring)]);
+        assert_eq!(matcher.find_in("a", "more drama", now).ids, vec![1, 2]);
+        assert_eq!(matcher.find_in("b", "more drama", now).ids, vec![2]);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(rule(1, "spam", MatchMode::Regex).validate(), Ok(()));
+        assert_eq!(rule(1, "a*", MatchMode::Regex).validate(), Err(RuleError::MatchesEmpty));
+        assert_eq!(rule(1, "^", MatchMode::Regex).validate(), Err(RuleError::MatchesEmpty));
+        assert_eq!(rule(1, ".", MatchMode::Regex).validate(), Err(RuleError::MatchesEverything));
+        assert_eq!(rule(1, r"\w{1000}{1000}", MatchMode::Regex).validate(), Err(RuleError::TooComplex));
+        assert_eq!(rule(1, "\u{200B}", MatchMode::Substring).validate(), Err(RuleError::MatchesEmpty));
+        assert_eq!(rule(1, "   ", MatchMode::WholeWord).validate(), Err(RuleError::MatchesEmpty));
+    }
+
+    #[test]
+    fn test_match_budget() {
+        let rules = vec![
+            rule(1, "spam", MatchMode::Substring),
+            Rule { normalize: NormalizeOptions::NONE, ..rule(2, "spam", MatchMode::Substring) },
+            rule(3, "sp[a4]m", MatchMode::Regex),
+            Rule { normalize: NormalizeOptions::NONE, ..rule(4, "sp[a4]m", MatchMode::Regex) },
+        ];
+        let (matcher, _) = Matcher::build(rules);
+        assert_eq!(matcher.find("spam", Utc::now()), vec![1, 2, 3, 4]);
+
+        // Plain words are checked in every group, only the regex sets are skipped
+        let found = matcher.find_within("spam", Utc::now(), Duration::ZERO);
+        assert_eq!(found, Found { ids: vec![1, 2], skipped: 2 });
+    }
+
+    #[test]
+    fn test_match_budget_single_group() {
+        // With the default normalization every regex rule is in one group
+        let rules: Vec<Rule> = (0..70).map(|i| rule(i, &format!(r"spam{}\s+now", i), MatchMode::Regex)).collect();
+        let (matcher, rejected) = Matcher::build(rules);
+        assert!(rejected.is_empty());
+        assert_eq!(matcher.find("spam65   now", Utc::now()), vec![65]);
+
+        // The budget is checked between sets of regexes, not once per group
+        let found = matcher.find_within("spam65   now", Utc::now(), Duration::ZERO);
+        assert_eq!(found, Found { ids: vec![], skipped: 3 });
+    }
+
+    #[test]
+    fn test_regex_set_too_big() {
+        let patterns = vec![r"cheap\s+viewers".to_string(), r"buy\s+followers".to_string()];
+        assert!(compile_regex_set(&patterns, REGEX_SIZE_LIMIT).is_ok());
+        assert_eq!(compile_regex_set(&patterns, 100).unwrap_err(), RuleError::TooComplex);
+    }
+
+    #[test]
+    fn test_explain() {
+        let (matcher, _) = Matcher::build(vec![
+            rule(1, "cheap viewers", MatchMode::Substring),
+            rule(2, r"buy\s+\w+", MatchMode::Regex),
+            rule(3, "Bot", MatchMode::WholeWord),
+        ]);
+        let text = "ⓒheap v1ewers, buy   followers from a Bot";
+        let ids = matcher.find(text, Utc::now());
+        assert_eq!(ids, vec![1, 2, 3]);
+
+        let matches = matcher.explain(text, &ids);
+        let matched: Vec<&str> = matches.iter().map(|m| m.matched.as_str()).collect();
+        assert_eq!(matched, vec!["cheap viewers", "buy   followers", "Bot"]);
+        assert_eq!(matches[1].pattern, r"buy\s+\w+");
+    }
+}
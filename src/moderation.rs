@@ -0,0 +1,357 @@
+use entity::sea_orm_active_enums::RuleAction;
+use sea_orm::FromQueryResult;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use crate::flood::Violation;
+use crate::matcher::{Rule, RuleMatch};
+use crate::settings::{Exemption, ExemptionCheck};
+use crate::usernames::UsernameMatch;
+
+/// Timeout length used when a timeout rule does not set its own duration
+pub const DEFAULT_TIMEOUT_SECS: u32 = 600;
+
+/// Names of the actions an unban reverses, as stored in the audit log
+pub const REVERSIBLE_ACTIONS: [&str; 2] = [Action::Ban.name(), Action::Timeout(0).name()];
+
+/**
+ * Enforcement action taken against a chatter. In settings it is written as
+ * `"delete"`, `{"timeout": 600}` or `"ban"`.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Delete,
+    Timeout(u32),
+    Ban,
+}
+
+impl Action {
+    /**
+     * Action for a matched rule, rules without an override ban
+     */
+    pub fn from_rule(rule: &Rule) -> Action {
+        Action::from_rule_action(rule.action, rule.timeout_duration)
+    }
+
+    /**
+     * Action for a rule's `action` and `timeout_duration` columns
+     */
+    pub fn from_rule_action(action: Option<RuleAction>, timeout_duration: Option<u32>) -> Action {
+        match action {
+            Some(RuleAction::Delete) => Action::Delete,
+            Some(RuleAction::Timeout) => Action::Timeout(timeout_duration.unwrap_or(DEFAULT_TIMEOUT_SECS)),
+            Some(RuleAction::Ban) | None => Action::Ban,
+        }
+    }
+
+    /**
+     * Action from its name in the audit log and its duration
+     */
+    pub fn from_name(name: &str, duration: Option<u32>) -> Option<Action> {
+        match name {
+            "delete" => Some(Action::Delete),
+            "timeout" => Some(Action::Timeout(duration.unwrap_or(DEFAULT_TIMEOUT_SECS))),
+            "ban" => Some(Action::Ban),
+            _ => None,
+        }
+    }
+
+    /**
+     * Name stored in the audit log
+     */
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Action::Delete => "delete",
+            Action::Timeout(_) => "timeout",
+            Action::Ban => "ban",
+        }
+    }
+
+    pub fn duration(&self) -> Option<u32> {
+        match self {
+            Action::Timeout(duration) => Some(*duration),
+            Action::Delete | Action::Ban => None,
+        }
+    }
+
+    /**
+     * Ordering key from the mildest to the harshest action
+     */
+    pub fn harshness(&self) -> (u8, u32) {
+        match self {
+            Action::Delete => (0, 0),
+            Action::Timeout(duration) => (1, *duration),
+            Action::Ban => (2, 0),
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::Delete => f.write_str("delete"),
+            Action::Timeout(duration) => write!(f, "timeout {}s", duration),
+            Action::Ban => f.write_str("ban"),
+        }
+    }
+}
+
+/**
+ * What was done to whom, written to the `moderation_actions` audit log
+ */
+#[derive(Debug, Clone)]
+pub struct AuditEntry {
+    pub channel: String,
+    pub target_user_id: String,
+    pub target_login: String,
+    pub action: Action,
+    pub rule_id: Option<i32>,
+    pub message_text: Option<String>,
+    pub actor: String,
+    pub trace: Option<DecisionTrace>,
+    /// Recorded without calling Helix
+    pub shadow: bool,
+}
+
+/**
+ * Everything that went into a moderation decision, persisted with the audit entry
+ */
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DecisionTrace {
+    pub matches: Vec<RuleMatch>,
+    /// Regex sets left unchecked because the matching budget was spent
+    pub skipped_regex_sets: usize,
+    pub spam_url: Option<String>,
+    /// Text of the spam wave the message was part of
+    pub spam_wave: Option<String>,
+    pub flood: Vec<Violation>,
+    /// Username pattern the login matched, on a join or first message
+    pub username: Option<UsernameMatch>,
+    /// `!nuke` the action was part of, `!unnuke` reverses them together
+    pub nuke: Option<String>,
+    pub decided_by: Option<i32>,
+    pub action: Option<String>,
+    /// The action is only recorded, either by the channel's mode or a canary rule
+    pub shadow: bool,
+    /// Shadowed because the circuit breaker tripped
+    pub circuit_breaker: bool,
+    pub seen: bool,
+    pub trusted: bool,
+    pub exemptions: Vec<ExemptionCheck>,
+    pub exemption: Option<Exemption>,
+}
+
+impl DecisionTrace {
+    /**
+     * Short explanation for chat
+     */
+    pub fn summary(&self) -> String {
+        let mut parts = vec![];
+
+        match &self.action {
+            Some(action) if self.shadow => parts.push(format!("{} (shadow)", action)),
+            Some(action) => parts.push(action.clone()),
+            None => parts.push("no action".to_string()),
+        }
+
+        for rule_match in &self.matches {
+            let decided = if self.decided_by == Some(rule_match.rule_id) { "*" } else { "" };
+            parts.push(format!("rule #{}{} \"{}\" matched \"{}\"", rule_match.rule_id, decided, rule_match.pattern, rule_match.matched));
+        }
+        if self.skipped_regex_sets > 0 {
+            parts.push(format!("{} regex sets skipped over the time budget", self.skipped_regex_sets));
+        }
+        if let Some(url) = &self.spam_url {
+            parts.push(format!("spam URL {}", url));
+        }
+        if let Some(phrase) = &self.spam_wave {
+            parts.push(format!("spam wave \"{}\"", phrase));
+        }
+        for violation in &self.flood {
+            parts.push(format!("{} ({})", violation, violation.action));
+        }
+        if let Some(username) = &self.username {
+            parts.push(username.to_string());
+        }
+        if let Some(nuke) = &self.nuke {
+            parts.push(format!("nuke {}", nuke));
+        }
+        if self.circuit_breaker {
+            parts.push("held by circuit breaker".to_string());
+        }
+
+        parts.push(if self.seen { "seen before" } else { "first message" }.to_string());
+        if self.trusted {
+            parts.push("trusted".to_string());
+        }
+
+        let checked: Vec<String> = self.exemptions.iter().map(|check| check.exemption.to_string()).collect();
+        match self.exemption {
+            Some(exemption) => parts.push(format!("exempt as {}", exemption)),
+            None => parts.push(format!("not exempt (checked {})", checked.join("/"))),
+        }
+
+        parts.join("; ")
+    }
+
+    /**
+     * Reason sent to Twitch with an action, naming what decided it without
+     * revealing the rule's pattern
+     */
+    pub fn reason(&self, action: Action) -> String {
+        let flood = self.flood.iter().find(|violation| violation.action == action).or(self.flood.first());
+        let cause = if self.username.is_some() {
+            "a username matching a banned pattern".to_string()
+        } else if self.spam_wave.is_some() {
+            "taking part in a spam wave".to_string()
+        } else if let Some(rule_id) = self.decided_by {
+            format!("matching banned word rule #{}", rule_id)
+        } else if self.spam_url.is_some() {
+            "posting a spam URL".to_string()
+        } else if let Some(violation) = flood {
+            format!("flooding ({})", violation)
+        } else {
+            "breaking the chat rules".to_string()
+        };
+
+        match action {
+            Action::Delete => format!("Message deleted for {}", cause),
+            Action::Timeout(_) => format!("Timed out for {}", cause),
+            Action::Ban => format!("Banned for {}", cause),
+        }
+    }
+}
+
+/**
+ * Enforced bans and timeouts by a rule and how many of them were reversed
+ */
+#[derive(Debug, Clone, PartialEq, Eq, FromQueryResult)]
+pub struct RulePrecision {
+    pub rule_id: i32,
+    pub actions: i64,
+    pub false_positives: i64,
+}
+
+impl RulePrecision {
+    /**
+     * Share of the rule's actions that were not reversed
+     */
+    pub fn precision(&self) -> f64 {
+        if self.actions == 0 {
+            return 1.0;
+        }
+        (self.actions - self.false_positives) as f64 / self.actions as f64
+    }
+}
+
+impl fmt::Display for RulePrecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f, "rule #{}: {} actions, {} reversed, precision {:.0}%",
+            self.rule_id, self.actions, self.false_positives, self.precision() * 100.0,
+        )
+    }
+}
+
+/**
+ * Pick the rule that decides the action: enforced rules before canaries, then
+ * the highest severity, then the harshest action
+ */
+pub fn decide<'a>(rules: impl IntoIterator<Item = &'a Rule>) -> Option<(Action, &'a Rule)> {
+    rules
+        .into_iter()
+        .map(|rule| (Action::from_rule(rule), rule))
+        .max_by_key(|(action, rule)| (!rule.canary, rule.severity, action.harshness()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flood::LimitKind;
+    use entity::sea_orm_active_enums::MatchMode;
+
+    fn rule(id: i32, severity: i32, action: Option<RuleAction>, timeout_duration: Option<u32>) -> Rule {
+        Rule {
+            severity,
+            action,
+            timeout_duration,
+            ..Rule::new(id, "spam", MatchMode::Substring)
+        }
+    }
+
+    #[test]
+    fn test_decide() {
+        let delete = rule(1, 5, Some(RuleAction::Delete), None);
+        let ban = rule(2, 1, None, None);
+        let timeout = rule(3, 5, Some(RuleAction::Timeout), Some(60));
+
+        assert_eq!(decide([&ban]).map(|(action, _)| action), Some(Action::Ban));
+        assert_eq!(decide([&delete, &ban]).map(|(action, rule)| (action, rule.id)), Some((Action::Delete, 1)));
+        assert_eq!(decide([&delete, &ban, &timeout]).map(|(action, rule)| (action, rule.id)), Some((Action::Timeout(60), 3)));
+        assert!(decide([]).is_none());
+
+        let canary = Rule { canary: true, ..rule(4, 9, None, None) };
+        assert_eq!(decide([&delete, &canary]).map(|(_, rule)| rule.id), Some(1));
+        assert_eq!(decide([&canary]).map(|(_, rule)| rule.id), Some(4));
+    }
+
+    #[test]
+    fn test_trace_summary() {
+        let trace = DecisionTrace {
+            matches: vec![RuleMatch { rule_id: 3, pattern: "cheap viewers".to_string(), matched: "cheap viewers".to_string() }],
+            decided_by: Some(3),
+            action: Some(Action::Ban.to_string()),
+            exemptions: vec![ExemptionCheck { exemption: Exemption::Moderator, matched: false }],
+            ..Default::default()
+        };
+        assert_eq!(
+            trace.summary(),
+            "ban; rule #3* \"cheap viewers\" matched \"cheap viewers\"; first message; not exempt (checked moderator)",
+        );
+
+        let skipped = DecisionTrace { skipped_regex_sets: 2, ..trace.clone() };
+        assert!(skipped.summary().contains("; 2 regex sets skipped over the time budget; "));
+
+        let shadow = DecisionTrace { shadow: true, ..trace };
+        assert!(shadow.summary().starts_with("ban (shadow); "));
+    }
+
+    #[test]
+    fn test_trace_reason() {
+        let rule = DecisionTrace { decided_by: Some(3), ..Default::default() };
+        assert_eq!(rule.reason(Action::Ban), "Banned for matching banned word rule #3");
+        assert_eq!(rule.reason(Action::Delete), "Message deleted for matching banned word rule #3");
+
+        let spam_url = DecisionTrace { spam_url: Some("https://cheap-viewers.example".to_string()), ..Default::default() };
+        assert_eq!(spam_url.reason(Action::Ban), "Banned for posting a spam URL");
+
+        let flood = DecisionTrace {
+            flood: vec![
+                Violation { limit: LimitKind::Length, value: 600.0, max: 500.0, action: Action::Delete },
+                Violation { limit: LimitKind::Rate, value: 12.0, max: 10.0, action: Action::Timeout(60) },
+            ],
+            ..Default::default()
+        };
+        assert_eq!(flood.reason(Action::Timeout(60)), "Timed out for flooding (message rate 12 > 10)");
+
+        let wave = DecisionTrace { spam_wave: Some("cheap viewers".to_string()), ..Default::default() };
+        assert_eq!(wave.reason(Action::Delete), "Message deleted for taking part in a spam wave");
+    }
+
+    #[test]
+    fn test_precision() {
+        let stats = RulePrecision { rule_id: 3, actions: 8, false_positives: 2 };
+        assert_eq!(stats.precision(), 0.75);
+        assert_eq!(stats.to_string(), "rule #3: 8 actions, 2 reversed, precision 75%");
+        assert_eq!(RulePrecision { rule_id: 3, actions: 0, false_positives: 0 }.precision(), 1.0);
+    }
+
+    #[test]
+    fn test_default_timeout() {
+        let timeout = rule(1, 1, Some(RuleAction::Timeout), None);
+        assert_eq!(Action::from_rule(&timeout), Action::Timeout(DEFAULT_TIMEOUT_SECS));
+        assert_eq!(Action::from_name("timeout", Some(60)), Some(Action::Timeout(60)));
+        assert_eq!(Action::from_name("warn", None), None);
+    }
+}
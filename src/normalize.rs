@@ -0,0 +1,218 @@
+use serde::{Deserialize, Serialize};
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+use unicode_security::skeleton;
+
+/**
+ * Steps of the normalization pipeline applied before matching
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct NormalizeOptions {
+    pub nfkc: bool,
+    pub strip_invisible: bool,
+    pub confusables: bool,
+    pub leetspeak: bool,
+    pub collapse_repeats: bool,
+}
+
+impl NormalizeOptions {
+    pub const NONE: NormalizeOptions = NormalizeOptions {
+        nfkc: false,
+        strip_invisible: false,
+        confusables: false,
+        leetspeak: false,
+        collapse_repeats: false,
+    };
+
+    pub const ALL: NormalizeOptions = NormalizeOptions {
+        nfkc: true,
+        strip_invisible: true,
+        confusables: true,
+        leetspeak: true,
+        collapse_repeats: true,
+    };
+
+    /// Used for spam URLs, digits and doubled letters are significant in domains
+    pub const URLS: NormalizeOptions = NormalizeOptions {
+        leetspeak: false,
+        collapse_repeats: false,
+        ..NormalizeOptions::ALL
+    };
+
+    /**
+     * Defaults for a banned word. Regexes only get the steps that keep their
+     * character classes meaningful.
+     */
+    pub fn defaults_for(is_regex: bool) -> NormalizeOptions {
+        if is_regex {
+            NormalizeOptions {
+                nfkc: true,
+                strip_invisible: true,
+                ..NormalizeOptions::NONE
+            }
+        } else {
+            NormalizeOptions::ALL
+        }
+    }
+}
+
+/**
+ * Per banned word opt-in/opt-out of each step, stored as JSON in
+ * `banned_words.normalization`. Unset steps use [`NormalizeOptions::defaults_for`].
+ */
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NormalizeOverrides {
+    pub nfkc: Option<bool>,
+    pub strip_invisible: Option<bool>,
+    pub confusables: Option<bool>,
+    pub leetspeak: Option<bool>,
+    pub collapse_repeats: Option<bool>,
+}
+
+impl NormalizeOverrides {
+    pub fn from_json(value: &serde_json::Value) -> Result<NormalizeOverrides, serde_json::Error> {
+        if value.is_null() {
+            return Ok(NormalizeOverrides::default());
+        }
+        NormalizeOverrides::deserialize(value)
+    }
+
+    pub fn resolve(&self, is_regex: bool) -> NormalizeOptions {
+        let defaults = NormalizeOptions::defaults_for(is_regex);
+        NormalizeOptions {
+            nfkc: self.nfkc.unwrap_or(defaults.nfkc),
+            strip_invisible: self.strip_invisible.unwrap_or(defaults.strip_invisible),
+            confusables: self.confusables.unwrap_or(defaults.confusables),
+            leetspeak: self.leetspeak.unwrap_or(defaults.leetspeak),
+            collapse_repeats: self.collapse_repeats.unwrap_or(defaults.collapse_repeats),
+        }
+    }
+}
+
+/**
+ * Run the enabled normalization steps over a text
+ */
+pub fn normalize(text: &str, options: NormalizeOptions) -> String {
+    let mut text = if options.strip_invisible {
+        text.chars().filter(|c| !is_invisible(*c)).collect()
+    } else {
+        text.to_string()
+    };
+
+    if options.nfkc {
+        text = text.nfkc().collect();
+    }
+
+    if options.leetspeak {
+        let chars: Vec<char> = text.chars().collect();
+        text = chars.iter().enumerate().map(|(i, c)| {
+            // Symbols only stand in for letters inside a word, "hi!" and "@user"
+            // stay as they are. A "$" also ends words, as in "follower$".
+            let after_word = i > 0 && chars[i - 1].is_alphanumeric();
+            let before_word = chars.get(i + 1).is_some_and(|next| next.is_alphanumeric());
+            fold_leetspeak(*c, after_word && before_word, after_word)
+        }).collect();
+    }
+
+    if options.confusables {
+        text = skeleton(&text).filter(|c| !is_combining_mark(*c)).collect();
+    }
+
+    if options.collapse_repeats {
+        let mut collapsed = String::with_capacity(text.len());
+        let mut previous = None;
+        for c in text.chars() {
+            if previous != Some(c) || !c.is_alphabetic() {
+                collapsed.push(c);
+            }
+            previous = Some(c);
+        }
+        text = collapsed;
+    }
+
+    text
+}
+
+/**
+ * Characters that render as nothing (or as a blank) and are used to split words
+ */
+fn is_invisible(c: char) -> bool {
+    matches!(c,
+        '\u{00AD}'
+        | '\u{034F}'
+        | '\u{061C}'
+        | '\u{115F}' | '\u{1160}'
+        | '\u{17B4}' | '\u{17B5}'
+        | '\u{180B}'..='\u{180F}'
+        | '\u{200B}'..='\u{200F}'
+        | '\u{202A}'..='\u{202E}'
+        | '\u{2060}'..='\u{206F}'
+        | '\u{2800}'
+        | '\u{3164}'
+        | '\u{FE00}'..='\u{FE0F}'
+        | '\u{FEFF}'
+        | '\u{FFA0}'
+        | '\u{E0000}'..='\u{E007F}'
+        | '\u{E0100}'..='\u{E01EF}'
+    )
+}
+
+fn fold_leetspeak(c: char, inside_word: bool, after_word: bool) -> char {
+    match c {
+        '0' => 'o',
+        '1' => 'i',
+        '!' | '|' if inside_word => 'i',
+        '3' => 'e',
+        '4' => 'a',
+        '@' if inside_word => 'a',
+        '5' => 's',
+        '$' if after_word => 's',
+        '7' => 't',
+        '8' => 'b',
+        '9' => 'g',
+        _ => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(message: &str, word: &str, options: NormalizeOptions) -> bool {
+        normalize(message, options).contains(&normalize(word, options))
+    }
+
+    #[test]
+    fn test_normalize_steps() {
+        assert_eq!(normalize("ⓒheap", NormalizeOptions { nfkc: true, ..NormalizeOptions::NONE }), "cheap");
+        assert_eq!(normalize("vi\u{200B}ewers", NormalizeOptions { strip_invisible: true, ..NormalizeOptions::NONE }), "viewers");
+        assert_eq!(normalize("v1ew3rs", NormalizeOptions { leetspeak: true, ..NormalizeOptions::NONE }), "viewers");
+        assert_eq!(normalize("v!ew$rs, hi! @bot", NormalizeOptions { leetspeak: true, ..NormalizeOptions::NONE }), "viewsrs, hi! @bot");
+        assert_eq!(normalize("cheap viewer$ b!tch!", NormalizeOptions { leetspeak: true, ..NormalizeOptions::NONE }), "cheap viewers bitch!");
+        assert_eq!(normalize("viiieeewers", NormalizeOptions { collapse_repeats: true, ..NormalizeOptions::NONE }), "viewers");
+        assert_eq!(normalize("v1ewers", NormalizeOptions::NONE), "v1ewers");
+    }
+
+    #[test]
+    fn test_spam_variants_match() {
+        let word = "cheap viewers";
+        assert!(matches("ⓒheap viewers", word, NormalizeOptions::ALL));
+        assert!(matches("cheap vi\u{200B}ewers", word, NormalizeOptions::ALL));
+        assert!(matches("сheap viеwers", word, NormalizeOptions::ALL)); // Cyrillic с and е
+        assert!(matches("cheap v1ewers", word, NormalizeOptions::ALL));
+        assert!(matches("cheeeap viewwwers", word, NormalizeOptions::ALL));
+        assert!(!matches("cheap v1ewers", word, NormalizeOptions::defaults_for(true)));
+    }
+
+    #[test]
+    fn test_overrides() {
+        let overrides = NormalizeOverrides::from_json(&serde_json::json!({ "leetspeak": false })).unwrap();
+        let options = overrides.resolve(false);
+        assert!(!options.leetspeak);
+        assert!(options.confusables);
+
+        let options = NormalizeOverrides::from_json(&serde_json::json!({ "confusables": true })).unwrap().resolve(true);
+        assert!(options.confusables);
+        assert!(!options.leetspeak);
+    }
+}
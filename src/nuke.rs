@@ -0,0 +1,194 @@
+use chrono::{DateTime, Duration, Utc};
+use entity::sea_orm_active_enums::MatchMode;
+use std::collections::HashMap;
+use crate::matcher::{Matcher, Rule};
+use crate::moderation::Action;
+use crate::recent::RecentMessage;
+
+/// Lookback when the command does not give one
+pub const DEFAULT_LOOKBACK_SECS: u32 = 5 * 60;
+
+/// Longest lookback, the recent-message buffer keeps no more
+pub const MAX_LOOKBACK_SECS: u32 = 30 * 60;
+
+/// Action when the command does not give one
+pub const DEFAULT_ACTION: Action = Action::Timeout(600);
+
+/// How long a previewed nuke can be confirmed
+pub const CONFIRM_SECS: i64 = 60;
+
+/**
+ * What to nuke: messages matching a phrase or regex within the lookback
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NukeSpec {
+    pub pattern: String,
+    /// `CaseInsensitive` for phrases, `Regex` for `/regex/`
+    pub mode: MatchMode,
+    pub lookback_secs: u32,
+    pub action: Action,
+}
+
+impl NukeSpec {
+    /**
+     * Parse `<phrase|/regex/> [lookback] [action]`, e.g. `buy followers 10m ban`.
+     * Lookbacks are like `90s`, `5m` or `1h`, actions are `ban`, `delete`,
+     * `timeout` or `timeout:10m`. A lone word is always the phrase.
+     */
+    pub fn parse(args: &str) -> Option<NukeSpec> {
+        let mut words: Vec<&str> = args.split_whitespace().collect();
+        let action = match words.last().filter(|_| words.len() > 1).and_then(|word| parse_action(word)) {
+            Some(action) => {
+                words.pop();
+                action
+            },
+            None => DEFAULT_ACTION,
+        };
+        let lookback_secs = match words.last().filter(|_| words.len() > 1).and_then(|word| parse_duration(word)) {
+            Some(secs) => {
+                words.pop();
+                secs.min(MAX_LOOKBACK_SECS)
+            },
+            None => DEFAULT_LOOKBACK_SECS,
+        };
+
+        let pattern = words.join(" ");
+        let (pattern, mode) = match pattern.strip_prefix('/').and_then(|pattern| pattern.strip_suffix('/')) {
+            Some(regex) => (regex.to_string(), MatchMode::Regex),
+            None => (pattern, MatchMode::CaseInsensitive),
+        };
+        if pattern.is_empty() {
+            return None;
+        }
+
+        Some(NukeSpec { pattern, mode, lookback_secs, action })
+    }
+
+    /**
+     * Matcher for the pattern, rejecting patterns that would match everything
+     */
+    pub fn matcher(&self) -> Result<Matcher, String> {
+        let rule = Rule::new(0, &self.pattern, self.mode);
+        rule.validate().map_err(|e| e.to_string())?;
+        let (matcher, errors) = Matcher::build(vec![rule]);
+        match errors.into_iter().next() {
+            Some((_, e)) => Err(e.to_string()),
+            None => Ok(matcher),
+        }
+    }
+}
+
+fn parse_duration(word: &str) -> Option<u32> {
+    let unit = match word.chars().last()? {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        _ => return None,
+    };
+    let value: u32 = word[..word.len() - 1].parse().ok()?;
+    value.checked_mul(unit).filter(|secs| *secs > 0)
+}
+
+fn parse_action(word: &str) -> Option<Action> {
+    match word.to_lowercase().as_str() {
+        "ban" => Some(Action::Ban),
+        "delete" => Some(Action::Delete),
+        "timeout" => Some(DEFAULT_ACTION),
+        word => word.strip_prefix("timeout:").and_then(parse_duration).map(Action::Timeout),
+    }
+}
+
+/**
+ * Messages to act on: unexempted matches since the given time. Deletions
+ * need every message, bans and timeouts only the latest one per user.
+ */
+pub fn targets<'a>(
+    matcher: &Matcher,
+    action: Action,
+    messages: impl Iterator<Item = &'a RecentMessage>,
+    now: DateTime<Utc>,
+) -> Vec<&'a RecentMessage> {
+    let matched = messages.filter(|message| !message.exempt && !matcher.find(&message.text, now).is_empty());
+    if action == Action::Delete {
+        return matched.collect();
+    }
+
+    let mut latest: HashMap<&str, &RecentMessage> = HashMap::new();
+    for message in matched {
+        latest.insert(message.user_id.as_str(), message);
+    }
+    let mut targets: Vec<&RecentMessage> = latest.into_values().collect();
+    targets.sort_by_key(|message| message.at);
+    targets
+}
+
+/**
+ * A previewed nuke waiting for `!nuke confirm`
+ */
+#[derive(Debug, Clone)]
+pub struct PendingNuke {
+    pub spec: NukeSpec,
+    pub moderator: String,
+    pub previewed_at: DateTime<Utc>,
+}
+
+impl PendingNuke {
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        now - self.previewed_at > Duration::seconds(CONFIRM_SECS)
+    }
+
+    /**
+     * Start of the window, the lookback counts back from the preview
+     */
+    pub fn since(&self) -> DateTime<Utc> {
+        self.previewed_at - Duration::seconds(self.spec.lookback_secs.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(NukeSpec::parse("buy followers"), Some(NukeSpec {
+            pattern: "buy followers".to_string(),
+            mode: MatchMode::CaseInsensitive,
+            lookback_secs: DEFAULT_LOOKBACK_SECS,
+            action: DEFAULT_ACTION,
+        }));
+        let spec = NukeSpec::parse("/b[u]y\\s+f0llowers/ 10m ban").unwrap();
+        assert_eq!((spec.pattern.as_str(), spec.mode, spec.lookback_secs, spec.action), ("b[u]y\\s+f0llowers", MatchMode::Regex, 600, Action::Ban));
+        assert_eq!(NukeSpec::parse("spam 2h timeout:90s").unwrap().lookback_secs, MAX_LOOKBACK_SECS);
+        assert_eq!(NukeSpec::parse("spam timeout:90s").unwrap().action, Action::Timeout(90));
+        assert_eq!(NukeSpec::parse("delete").unwrap().pattern, "delete");
+        assert_eq!(NukeSpec::parse("5m ban").unwrap().pattern, "5m");
+        assert_eq!(NukeSpec::parse(""), None);
+    }
+
+    #[test]
+    fn test_targets() {
+        let now = Utc::now();
+        let message = |user_id: &str, text: &str, exempt: bool| RecentMessage {
+            at: now,
+            user_id: user_id.to_string(),
+            login: format!("user{}", user_id),
+            message_id: format!("{}-{}", user_id, text.len()),
+            text: text.to_string(),
+            actioned: false,
+            exempt,
+        };
+        let messages = [
+            message("1", "BUY followers now", false),
+            message("1", "buy followers cheap", false),
+            message("2", "who would buy followers lol", true),
+            message("3", "hello", false),
+        ];
+
+        let matcher = NukeSpec::parse("buy followers").unwrap().matcher().unwrap();
+        assert_eq!(targets(&matcher, Action::Ban, messages.iter(), now).len(), 1);
+        assert_eq!(targets(&matcher, Action::Delete, messages.iter(), now).len(), 2);
+
+        assert!(NukeSpec::parse("/.*/").unwrap().matcher().is_err());
+    }
+}
@@ -1,7 +1,40 @@
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
 
 #[derive(Parser, Debug, Clone)]
-#[clap(author, version, about, long_about = None)]
+#[clap(author, version, about, long_about = None, args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    /// Options for running the bot without a subcommand
+    #[clap(flatten)]
+    pub opts: Option<Opts>,
+
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Connect to Twitch and moderate the channels, the default without a subcommand
+    Run(Opts),
+    /// Import known-bot lists (text, CSV or JSON) into `users.is_bot` and exit
+    ImportBots {
+        #[clap(flatten)]
+        opts: Opts,
+        /// Lists to import, the configured bot lists if none are given
+        lists: Vec<String>,
+    },
+    /// Run the moderation rules over a message log and list what they would action, without Twitch
+    Check(CheckOpts),
+    /// Replay a recorded IRC session through the bot and list what it would do, without Twitch or a database
+    Replay(ReplayOpts),
+}
+
+/**
+ * Options of the bot itself, required to run it or import bot lists
+ */
+#[derive(Parser, Debug, Clone)]
+// Flattened into `Cli`, so this doc comment must not become the program's about
+#[clap(about = None, long_about = None)]
 pub struct Opts {
     #[clap(long, env, hide_env = true)]
     pub twitch_client_id: twitch_api::twitch_oauth2::ClientId,
@@ -17,4 +50,112 @@ pub struct Opts {
     
     #[clap(long, env, hide_env = true)]
     pub database_url: String,
-}
\ No newline at end of file
+
+    /// Address for the admin HTTP API, disabled when unset
+    #[clap(long, env, hide_env = true)]
+    pub admin_listen: Option<std::net::SocketAddr>,
+
+    /// Bearer token required by the admin HTTP API
+    #[clap(long, env, hide_env = true)]
+    pub admin_token: Option<String>,
+
+    /// Known-bot lists imported every hour, `source=path` or a path tagged with its file name
+    #[clap(long, env, hide_env = true, value_delimiter = ',')]
+    pub bot_lists: Vec<String>,
+
+    /// EventSub WebSocket URL, e.g. wss://eventsub.wss.twitch.tv/ws, disabled when unset
+    #[clap(long, env, hide_env = true)]
+    pub eventsub_url: Option<String>,
+
+    /// Address for the EventSub webhook receiver, disabled when unset
+    #[clap(long, env, hide_env = true)]
+    pub eventsub_webhook_listen: Option<std::net::SocketAddr>,
+
+    /// Public URL Twitch delivers webhook notifications to, subscriptions are created for it when set
+    #[clap(long, env, hide_env = true)]
+    pub eventsub_webhook_callback: Option<String>,
+
+    /// Secret for signing webhook notifications, 10 to 100 characters
+    #[clap(long, env, hide_env = true)]
+    pub eventsub_webhook_secret: Option<String>,
+
+    /// Most enforcement actions per minute across all channels before the circuit breaker trips, 0 disables
+    #[clap(long, env, hide_env = true, default_value_t = 100)]
+    pub max_actions_per_minute: u32,
+
+    /// File to append the IRC lines the bot receives to, for replaying them later
+    #[clap(long, env, hide_env = true)]
+    pub record_irc: Option<PathBuf>,
+}
+
+impl Opts {
+    /**
+     * Options from the environment alone, exits with the missing ones if any
+     */
+    pub fn from_env() -> Opts {
+        Opts::parse_from([env!("CARGO_PKG_NAME")])
+    }
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct CheckOpts {
+    /// Message log, JSON lines or one message per line
+    pub log: PathBuf,
+
+    /// Banned words to check instead of the database's, JSON lines or one word or `/regex/` per line
+    #[clap(long)]
+    pub rules: Option<PathBuf>,
+
+    /// Channel the messages were posted in, for its settings and channel rules, unless a message names its own
+    #[clap(long)]
+    pub channel: Option<String>,
+
+    /// Treat every message as its author's first, regulars are otherwise left alone like in chat
+    #[clap(long)]
+    pub first_messages: bool,
+
+    /// List every message, not only the actioned ones
+    #[clap(long)]
+    pub all: bool,
+
+    /// Database to load rules, channel settings, trusted users, known users and username patterns from without `--rules`
+    #[clap(long, env, hide_env = true)]
+    pub database_url: Option<String>,
+}
+#[derive(Args, Debug, Clone)]
+pub struct ReplayOpts {
+    /// Session recorded with `--record-irc`
+    pub session: PathBuf,
+
+    /// Banned words, JSON lines or one word or `/regex/` per line like for `check`
+    #[clap(long)]
+    pub rules: Option<PathBuf>,
+
+    /// Channel settings as a JSON object by channel name, e.g. `{"channel": {"spam_waves": {"enabled": true}}}`
+    #[clap(long)]
+    pub settings: Option<PathBuf>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REQUIRED: [&str; 10] = [
+        "--twitch-client-id", "id",
+        "--twitch-client-secret", "secret",
+        "--twitch-token", "oauth:token",
+        "--twitch-username", "bot",
+        "--database-url", "postgres://localhost/bot",
+    ];
+
+    #[test]
+    fn test_top_level_flags() {
+        let cli = Cli::try_parse_from(["twitchbot2"].into_iter().chain(REQUIRED)).unwrap();
+        assert!(cli.command.is_none());
+        assert_eq!(cli.opts.unwrap().database_url, "postgres://localhost/bot");
+
+        let cli = Cli::try_parse_from(["twitchbot2", "check", "chat.log"]).unwrap();
+        assert!(matches!(cli.command, Some(Command::Check(_))));
+        assert!(Cli::try_parse_from(["twitchbot2", "--database-url", "postgres://localhost/bot", "check", "chat.log"]).is_err());
+    }
+}
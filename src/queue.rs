@@ -0,0 +1,178 @@
+use chrono::{DateTime, Duration, Utc};
+use entity::enforcement_queue;
+use std::collections::HashMap;
+use crate::helix::HelixError;
+use crate::moderation::Action;
+
+/// Attempts, including the first one, before a queued action is given up on
+pub const MAX_ATTEMPTS: i32 = 8;
+
+/// Audit log error of an action waiting in the queue for its first attempt
+pub const QUEUED: &str = "queued";
+
+/// How often the retry queue is checked for due actions
+pub const RETRY_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// How long a ban is remembered, bans lifted without an unban event would otherwise be skipped forever
+pub const BAN_MEMORY_HOURS: i64 = 24;
+
+const BASE_BACKOFF_SECS: i64 = 5;
+const MAX_BACKOFF_SECS: i64 = 600;
+
+/**
+ * An action to carry out through Helix, as stored in the `enforcement_queue` table
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Enforcement {
+    pub channel: String,
+    pub broadcaster_id: String,
+    pub target_user_id: String,
+    pub target_login: String,
+    pub action: Action,
+    pub message_id: Option<String>,
+    pub reason: String,
+}
+
+impl Enforcement {
+    /**
+     * Enforcement from a queued job, `None` if the stored action is unknown
+     */
+    pub fn from_job(job: &enforcement_queue::Model) -> Option<Enforcement> {
+        let duration = job.duration.and_then(|duration| u32::try_from(duration).ok());
+        Some(Enforcement {
+            channel: job.channel.clone(),
+            broadcaster_id: job.broadcaster_id.clone(),
+            target_user_id: job.target_user_id.clone(),
+            target_login: job.target_login.clone(),
+            action: Action::from_name(&job.action, duration)?,
+            message_id: job.message_id.clone(),
+            reason: job.reason.clone(),
+        })
+    }
+}
+
+/**
+ * Delay before the next attempt, doubling per attempt up to ten minutes
+ */
+pub fn backoff(attempts: i32) -> Duration {
+    let exponent = attempts.clamp(1, 16) as u32 - 1;
+    Duration::seconds((BASE_BACKOFF_SECS << exponent).min(MAX_BACKOFF_SECS))
+}
+
+/**
+ * When to try again after a failed attempt, never before the rate limit resets
+ */
+pub fn next_attempt(attempts: i32, error: &HelixError, now: DateTime<Utc>) -> DateTime<Utc> {
+    let after_backoff = now + backoff(attempts);
+    match error.retry_at {
+        Some(retry_at) if retry_at > after_backoff => retry_at,
+        _ => after_backoff,
+    }
+}
+
+/**
+ * Bans and timeouts carried out or queued per channel and user, so that a
+ * spammer sending several messages in a row is only actioned once
+ */
+#[derive(Debug, Default)]
+pub struct RecentActions {
+    actions: HashMap<(String, String), (Action, DateTime<Utc>)>,
+}
+
+impl RecentActions {
+    pub fn insert(&mut self, channel: &str, user_id: &str, action: Action, now: DateTime<Utc>) {
+        if action == Action::Delete {
+            return;
+        }
+        self.actions.retain(|_, (previous, at)| !is_expired(*previous, *at, now));
+        let key = (channel.to_string(), user_id.to_string());
+        if !self.covers(channel, user_id, action, now) {
+            self.actions.insert(key, (action, now));
+        }
+    }
+
+    pub fn remove(&mut self, channel: &str, user_id: &str) {
+        self.actions.remove(&(channel.to_string(), user_id.to_string()));
+    }
+
+    /**
+     * Whether an earlier action against the user already has the effect of
+     * this one. An expired earlier action is dropped.
+     */
+    pub fn covers(&mut self, channel: &str, user_id: &str, action: Action, now: DateTime<Utc>) -> bool {
+        let key = (channel.to_string(), user_id.to_string());
+        let Some((previous, at)) = self.actions.get(&key).copied() else {
+            return false;
+        };
+        if is_expired(previous, at, now) {
+            self.actions.remove(&key);
+            return false;
+        }
+
+        let until = |duration: u32| at + Duration::seconds(duration.into());
+        match (previous, action) {
+            (Action::Ban, _) => true,
+            (Action::Timeout(_), Action::Delete) => true,
+            (Action::Timeout(previous), Action::Timeout(duration)) => until(previous) >= now + Duration::seconds(duration.into()),
+            _ => false,
+        }
+    }
+}
+
+/**
+ * Whether an action no longer has any effect: its timeout ran out, or the ban
+ * is older than [`BAN_MEMORY_HOURS`]
+ */
+fn is_expired(action: Action, at: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+    match action {
+        Action::Timeout(duration) => at + Duration::seconds(duration.into()) <= now,
+        Action::Ban => at + Duration::hours(BAN_MEMORY_HOURS) <= now,
+        Action::Delete => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(backoff(1), Duration::seconds(5));
+        assert_eq!(backoff(3), Duration::seconds(20));
+        assert_eq!(backoff(12), Duration::seconds(600));
+
+        let now = Utc::now();
+        let mut error = HelixError { message: "too many requests".to_string(), status: Some(429), retry_at: Some(now + Duration::seconds(40)), permanent: false };
+        assert_eq!(next_attempt(1, &error, now), now + Duration::seconds(40));
+        error.retry_at = None;
+        assert_eq!(next_attempt(2, &error, now), now + Duration::seconds(10));
+    }
+
+    #[test]
+    fn test_recent_actions() {
+        let now = Utc::now();
+        let mut recent = RecentActions::default();
+        assert!(!recent.covers("channel", "123", Action::Ban, now));
+
+        recent.insert("channel", "123", Action::Timeout(600), now);
+        assert!(recent.covers("channel", "123", Action::Delete, now));
+        assert!(recent.covers("channel", "123", Action::Timeout(60), now));
+        assert!(!recent.covers("channel", "123", Action::Ban, now));
+        assert!(!recent.covers("channel", "123", Action::Delete, now + Duration::seconds(601)));
+        assert!(!recent.covers("other_channel", "123", Action::Delete, now));
+
+        recent.insert("channel", "123", Action::Ban, now);
+        assert!(recent.covers("channel", "123", Action::Timeout(86400), now));
+        recent.remove("channel", "123");
+        assert!(!recent.covers("channel", "123", Action::Delete, now));
+
+        // Expired timeouts and old bans are dropped
+        recent.insert("channel", "1", Action::Timeout(60), now);
+        recent.insert("channel", "2", Action::Ban, now);
+        assert!(!recent.covers("channel", "1", Action::Delete, now + Duration::seconds(60)));
+        assert_eq!(recent.actions.len(), 1);
+        recent.insert("channel", "3", Action::Timeout(60), now + Duration::hours(BAN_MEMORY_HOURS));
+        assert_eq!(recent.actions.len(), 1);
+        assert!(!recent.covers("channel", "2", Action::Ban, now + Duration::hours(BAN_MEMORY_HOURS)));
+    }
+}
@@ -0,0 +1,272 @@
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use twitch_api::helix::chat::{ChatSettings, UpdateChatSettingsBody};
+use crate::settings::{ChatPreset, RaidSettings};
+
+/// How often emergencies are checked for their cooldown
+pub const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Events kept per channel regardless of the window
+const MAX_WINDOW_EVENTS: usize = 5000;
+
+/**
+ * Signal that made the bot call an emergency
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum Trigger {
+    Joins(usize),
+    /// First messages out of all messages
+    FirstMessages(usize, usize),
+    BannedWords(usize),
+}
+
+impl fmt::Display for Trigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Trigger::Joins(joins) => write!(f, "{} joins", joins),
+            Trigger::FirstMessages(first, total) => write!(f, "{} of {} messages from first-time chatters", first, total),
+            Trigger::BannedWords(hits) => write!(f, "{} banned word hits", hits),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Window {
+    joins: VecDeque<DateTime<Utc>>,
+    /// Messages with whether they were a first message and hit a banned word
+    messages: VecDeque<(DateTime<Utc>, bool, bool)>,
+}
+
+/**
+ * Joins and messages per channel within the detection window
+ */
+#[derive(Debug, Default)]
+pub struct RaidDetector {
+    channels: HashMap<String, Window>,
+}
+
+impl RaidDetector {
+    pub fn join(&mut self, channel: &str, now: DateTime<Utc>) {
+        let joins = &mut self.channels.entry(channel.to_string()).or_default().joins;
+        if joins.len() >= MAX_WINDOW_EVENTS {
+            joins.pop_front();
+        }
+        joins.push_back(now);
+    }
+
+    pub fn message(&mut self, channel: &str, now: DateTime<Utc>, first: bool, banned_word: bool) {
+        let messages = &mut self.channels.entry(channel.to_string()).or_default().messages;
+        if messages.len() >= MAX_WINDOW_EVENTS {
+            messages.pop_front();
+        }
+        messages.push_back((now, first, banned_word));
+    }
+
+    /**
+     * The first signal over its limit within the window, if any
+     */
+    pub fn check(&mut self, channel: &str, settings: &RaidSettings, now: DateTime<Utc>) -> Option<Trigger> {
+        let window = self.channels.get_mut(channel)?;
+        let since = now - Duration::seconds(settings.window_secs.into());
+        while window.joins.front().is_some_and(|at| *at <= since) {
+            window.joins.pop_front();
+        }
+        while window.messages.front().is_some_and(|(at, _, _)| *at <= since) {
+            window.messages.pop_front();
+        }
+
+        let joins = window.joins.len();
+        if settings.max_joins.is_some_and(|max| joins > max) {
+            return Some(Trigger::Joins(joins));
+        }
+
+        let total = window.messages.len();
+        let first = window.messages.iter().filter(|(_, first, _)| *first).count();
+        if total >= settings.min_messages && settings.max_first_message_ratio.is_some_and(|max| first as f64 / total as f64 > max) {
+            return Some(Trigger::FirstMessages(first, total));
+        }
+
+        let hits = window.messages.iter().filter(|(_, _, banned_word)| *banned_word).count();
+        if settings.max_banned_word_hits.is_some_and(|max| hits > max) {
+            return Some(Trigger::BannedWords(hits));
+        }
+        None
+    }
+
+    /**
+     * Forget the channel's window, so that an attack is not detected again
+     * from the same events once the emergency ends
+     */
+    pub fn clear(&mut self, channel: &str) {
+        self.channels.remove(channel);
+    }
+}
+
+/**
+ * A channel's chat settings as far as presets touch them, `None` durations are off
+ */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChatState {
+    pub emote_only: bool,
+    pub followers_only_minutes: Option<u64>,
+    pub slow_mode_secs: Option<u64>,
+    pub subscribers_only: bool,
+    pub unique_chat: bool,
+}
+
+impl From<&ChatSettings> for ChatState {
+    fn from(settings: &ChatSettings) -> Self {
+        ChatState {
+            emote_only: settings.emote_mode,
+            followers_only_minutes: settings.follower_mode.then(|| settings.follower_mode_duration.unwrap_or(0)),
+            slow_mode_secs: settings.slow_mode.then(|| settings.slow_mode_wait_time.unwrap_or(0)),
+            subscribers_only: settings.subscriber_mode,
+            unique_chat: settings.unique_chat_mode,
+        }
+    }
+}
+
+/**
+ * An ongoing emergency in a channel
+ */
+#[derive(Debug, Clone)]
+pub struct Emergency {
+    pub trigger: Trigger,
+    pub until: DateTime<Utc>,
+    /// Settings before the preset, restored when it ends. `None` if nothing was applied.
+    pub previous: Option<ChatState>,
+    pub shield_mode: bool,
+}
+
+/**
+ * Update turning on the preset's settings
+ */
+pub fn apply_body(preset: &ChatPreset) -> UpdateChatSettingsBody {
+    let mut body = UpdateChatSettingsBody::default();
+    if preset.emote_only {
+        body.emote_mode = Some(true);
+    }
+    if let Some(minutes) = preset.followers_only_minutes {
+        body.follower_mode = Some(true);
+        body.follower_mode_duration = Some(minutes);
+    }
+    if let Some(secs) = preset.slow_mode_secs {
+        body.slow_mode = Some(true);
+        body.slow_mode_wait_time = Some(secs);
+    }
+    if preset.subscribers_only {
+        body.subscriber_mode = Some(true);
+    }
+    if preset.unique_chat {
+        body.unique_chat_mode = Some(true);
+    }
+    body
+}
+
+/**
+ * Update putting the settings the preset touched back as they were
+ */
+pub fn restore_body(preset: &ChatPreset, previous: &ChatState) -> UpdateChatSettingsBody {
+    let mut body = UpdateChatSettingsBody::default();
+    if preset.emote_only {
+        body.emote_mode = Some(previous.emote_only);
+    }
+    if preset.followers_only_minutes.is_some() {
+        body.follower_mode = Some(previous.followers_only_minutes.is_some());
+        body.follower_mode_duration = previous.followers_only_minutes;
+    }
+    if preset.slow_mode_secs.is_some() {
+        body.slow_mode = Some(previous.slow_mode_secs.is_some());
+        body.slow_mode_wait_time = previous.slow_mode_secs;
+    }
+    if preset.subscribers_only {
+        body.subscriber_mode = Some(previous.subscribers_only);
+    }
+    if preset.unique_chat {
+        body.unique_chat_mode = Some(previous.unique_chat);
+    }
+    body
+}
+
+/**
+ * Short description of a preset for chat, e.g. `followers-only (10m), slow mode (10s)`
+ */
+pub fn describe(preset: &ChatPreset, shield_mode: bool) -> String {
+    let mut parts = vec![];
+    if shield_mode {
+        parts.push("Shield Mode".to_string());
+    }
+    if preset.emote_only {
+        parts.push("emote-only".to_string());
+    }
+    if let Some(minutes) = preset.followers_only_minutes {
+        parts.push(format!("followers-only ({}m)", minutes));
+    }
+    if let Some(secs) = preset.slow_mode_secs {
+        parts.push(format!("slow mode ({}s)", secs));
+    }
+    if preset.subscribers_only {
+        parts.push("subscribers-only".to_string());
+    }
+    if preset.unique_chat {
+        parts.push("unique chat".to_string());
+    }
+    if parts.is_empty() {
+        return "no chat settings".to_string();
+    }
+    parts.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let now = Utc::now();
+        let settings = RaidSettings {
+            enabled: true,
+            max_joins: Some(3),
+            max_first_message_ratio: Some(0.5),
+            min_messages: 4,
+            max_banned_word_hits: Some(1),
+            ..Default::default()
+        };
+        let mut detector = RaidDetector::default();
+        for _ in 0..3 {
+            detector.join("channel", now);
+        }
+        assert_eq!(detector.check("channel", &settings, now), None);
+        detector.join("channel", now);
+        assert_eq!(detector.check("channel", &settings, now), Some(Trigger::Joins(4)));
+        assert_eq!(detector.check("channel", &settings, now + Duration::seconds(31)), None);
+
+        detector.message("channel", now, true, false);
+        detector.message("channel", now, true, false);
+        detector.message("channel", now, false, false);
+        assert_eq!(detector.check("channel", &settings, now), None);
+        detector.message("channel", now, false, true);
+        assert_eq!(detector.check("channel", &settings, now), None);
+        detector.message("channel", now, true, true);
+        assert_eq!(detector.check("channel", &settings, now), Some(Trigger::FirstMessages(3, 5)));
+
+        detector.clear("channel");
+        assert_eq!(detector.check("channel", &settings, now), None);
+        assert_eq!(detector.check("other", &settings, now), None);
+    }
+
+    #[test]
+    fn test_bodies() {
+        let preset = ChatPreset { emote_only: true, slow_mode_secs: None, ..Default::default() };
+        let body = apply_body(&preset);
+        assert_eq!((body.emote_mode, body.follower_mode, body.follower_mode_duration, body.slow_mode), (Some(true), Some(true), Some(10), None));
+
+        let previous = ChatState { followers_only_minutes: Some(0), slow_mode_secs: Some(30), ..Default::default() };
+        let body = restore_body(&preset, &previous);
+        assert_eq!((body.emote_mode, body.follower_mode, body.follower_mode_duration, body.slow_mode), (Some(false), Some(true), Some(0), None));
+
+        assert_eq!(describe(&ChatPreset::default(), true), "Shield Mode, followers-only (10m), slow mode (10s)");
+        assert_eq!(describe(&ChatPreset { followers_only_minutes: None, slow_mode_secs: None, ..Default::default() }, false), "no chat settings");
+    }
+}
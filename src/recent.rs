@@ -0,0 +1,115 @@
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, VecDeque};
+
+/// Messages kept per channel
+const MAX_MESSAGES: usize = 2000;
+
+/// How long messages are kept
+const MAX_AGE_MINUTES: i64 = 30;
+
+/**
+ * A chat message kept in the channel's buffer
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecentMessage {
+    pub at: DateTime<Utc>,
+    pub user_id: String,
+    pub login: String,
+    pub message_id: String,
+    pub text: String,
+    /// Whether the bot acted on the message or its author
+    pub actioned: bool,
+    /// Whether the author is exempt from moderation, e.g. a VIP
+    pub exempt: bool,
+}
+
+/**
+ * The latest chat messages per channel
+ */
+#[derive(Debug, Default)]
+pub struct RecentMessages {
+    channels: HashMap<String, VecDeque<RecentMessage>>,
+}
+
+impl RecentMessages {
+    pub fn push(&mut self, channel: &str, message: RecentMessage) {
+        let messages = self.channels.entry(channel.to_string()).or_default();
+        let since = message.at - Duration::minutes(MAX_AGE_MINUTES);
+        while messages.front().is_some_and(|previous| previous.at <= since) || messages.len() >= MAX_MESSAGES {
+            messages.pop_front();
+        }
+        messages.push_back(message);
+    }
+
+    /**
+     * Mark every buffered message of a user as acted on by the bot
+     */
+    pub fn mark_actioned(&mut self, channel: &str, user_id: &str) {
+        for message in self.channels.get_mut(channel).into_iter().flatten() {
+            if message.user_id == user_id {
+                message.actioned = true;
+            }
+        }
+    }
+
+    /**
+     * A user's messages since the given time, oldest first
+     */
+    pub fn by_user(&self, channel: &str, login: &str, since: DateTime<Utc>) -> Vec<&RecentMessage> {
+        self.since(channel, since).filter(|message| message.login == login).collect()
+    }
+
+    pub fn by_id(&self, channel: &str, message_id: &str) -> Option<&RecentMessage> {
+        self.channels.get(channel)?.iter().find(|message| message.message_id == message_id)
+    }
+
+    /**
+     * The channel's messages since the given time, oldest first
+     */
+    pub fn since<'a>(&'a self, channel: &str, since: DateTime<Utc>) -> impl Iterator<Item = &'a RecentMessage> + 'a {
+        self.channels.get(channel)
+            .into_iter()
+            .flatten()
+            .filter(move |message| message.at > since)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(seconds: i64, user_id: &str, text: &str) -> RecentMessage {
+        RecentMessage {
+            at: DateTime::from_timestamp(1_700_000_000 + seconds, 0).unwrap(),
+            user_id: user_id.to_string(),
+            login: format!("user{}", user_id),
+            message_id: format!("{}-{}", user_id, seconds),
+            text: text.to_string(),
+            actioned: false,
+            exempt: false,
+        }
+    }
+
+    #[test]
+    fn test_buffer() {
+        let mut recent = RecentMessages::default();
+        recent.push("channel", message(0, "1", "hello"));
+        recent.push("channel", message(10, "2", "hi"));
+        recent.push("channel", message(20, "1", "buy followers"));
+        recent.push("other", message(20, "1", "elsewhere"));
+
+        let since = message(5, "", "").at;
+        let texts: Vec<&str> = recent.by_user("channel", "user1", since).iter().map(|message| message.text.as_str()).collect();
+        assert_eq!(texts, vec!["buy followers"]);
+
+        recent.mark_actioned("channel", "1");
+        assert!(recent.by_id("channel", "1-0").unwrap().actioned);
+        assert!(!recent.by_id("channel", "2-10").unwrap().actioned);
+        assert!(!recent.by_id("other", "1-20").unwrap().actioned);
+
+        // Older messages fall out of the buffer
+        recent.push("channel", message(MAX_AGE_MINUTES * 60 + 5, "3", "later"));
+        assert!(recent.by_id("channel", "1-0").is_none());
+        assert_eq!(recent.since("channel", DateTime::<Utc>::MIN_UTC).count(), 3);
+    }
+}
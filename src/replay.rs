@@ -0,0 +1,213 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use twitch_irc::message::{AsRawIRC, IRCMessage, ServerMessage};
+use crate::helix::HelixCall;
+
+/// User ID the bot moderates as in a replay, unless it is already known
+pub const BOT_USER_ID: &str = "0";
+
+/**
+ * A line of a recorded session: an IRC line as received and when
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedLine {
+    /// Line number in the recording
+    pub number: usize,
+    pub at: DateTime<Utc>,
+    pub raw: String,
+}
+
+impl RecordedLine {
+    pub fn message(&self) -> Result<ServerMessage, String> {
+        let message = IRCMessage::parse(&self.raw).map_err(|e| format!("line {}: {}", self.number, e))?;
+        ServerMessage::try_from(message).map_err(|e| format!("line {}: {}", self.number, e))
+    }
+}
+
+/**
+ * Format of a recorded line: the time it was received, a space and the raw line
+ */
+pub fn format_line(at: DateTime<Utc>, raw: &str) -> String {
+    format!("{} {}", at.to_rfc3339_opts(SecondsFormat::Millis, true), raw)
+}
+
+/**
+ * Parse a recorded session. Empty lines and lines starting with `#` are
+ * skipped, so recordings can be trimmed and annotated by hand.
+ */
+pub fn parse_session(content: &str) -> Result<Vec<RecordedLine>, String> {
+    let mut lines = vec![];
+    for (i, line) in content.lines().enumerate() {
+        let number = i + 1;
+        let line = line.trim_end();
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (at, raw) = line.split_once(' ').ok_or_else(|| format!("line {}: expected a time and an IRC line", number))?;
+        let at = DateTime::parse_from_rfc3339(at).map_err(|e| format!("line {}: invalid time {}: {}", number, at, e))?;
+        lines.push(RecordedLine { number, at: at.to_utc(), raw: raw.to_string() });
+    }
+    Ok(lines)
+}
+
+/**
+ * Appends the IRC lines the bot receives to a file, one per line as they come
+ */
+pub struct Recorder {
+    file: std::fs::File,
+}
+
+impl Recorder {
+    pub fn open(path: &Path) -> std::io::Result<Recorder> {
+        let file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Recorder { file })
+    }
+
+    pub fn record(&mut self, at: DateTime<Utc>, message: &ServerMessage) -> std::io::Result<()> {
+        writeln!(self.file, "{}", format_line(at, &message.source().as_raw_irc()))
+    }
+}
+
+/**
+ * A chat message the bot sent
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SentMessage {
+    pub channel: String,
+    pub text: String,
+    /// ID of the message replied to
+    pub reply_to: Option<String>,
+}
+
+/**
+ * Takes the bot's chat messages in place of the IRC connection
+ */
+#[derive(Debug, Clone, Default)]
+pub struct Outbox {
+    sent: Arc<Mutex<Vec<SentMessage>>>,
+}
+
+impl Outbox {
+    pub fn push(&self, channel: &str, text: &str, reply_to: Option<&str>) {
+        self.sent.lock().unwrap().push(SentMessage {
+            channel: channel.to_string(),
+            text: text.to_string(),
+            reply_to: reply_to.map(str::to_string),
+        });
+    }
+
+    /**
+     * Messages sent since the last call
+     */
+    pub fn take(&self) -> Vec<SentMessage> {
+        std::mem::take(&mut *self.sent.lock().unwrap())
+    }
+}
+
+/**
+ * Something the bot did during a replay
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Output {
+    Chat(SentMessage),
+    Helix(HelixCall),
+}
+
+/**
+ * What the bot did on one line of a replay
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pub line: usize,
+    pub at: DateTime<Utc>,
+    pub output: Output,
+}
+
+/**
+ * Everything the bot did during a replay, in order
+ */
+#[derive(Debug, Default)]
+pub struct Transcript {
+    /// Rules left out, as in the `check` report
+    pub rejected: Vec<String>,
+    /// Lines replayed
+    pub lines: usize,
+    pub steps: Vec<Step>,
+}
+
+impl Transcript {
+    /**
+     * Rejected rules, then one line per step, e.g. `line 4 12:00:03: ban 103`,
+     * followed by the totals
+     */
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for rejection in &self.rejected {
+            let _ = writeln!(out, "{}", rejection);
+        }
+        for step in &self.steps {
+            let output = match &step.output {
+                Output::Chat(message) => match &message.reply_to {
+                    Some(id) => format!("reply to {} in #{}: {}", id, message.channel, message.text),
+                    None => format!("say in #{}: {}", message.channel, message.text),
+                },
+                Output::Helix(call) => call.describe(),
+            };
+            let _ = writeln!(out, "line {} {}: {}", step.line, step.at.format("%H:%M:%S"), output);
+        }
+
+        let calls = self.steps.iter().filter(|step| matches!(step.output, Output::Helix(_))).count();
+        let _ = writeln!(out, "{} lines replayed, {} Helix calls, {} chat messages", self.lines, calls, self.steps.len() - calls);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_session() {
+        let lines = parse_session(concat!(
+            "# spam wave\n",
+            "2026-10-19T12:00:00.000Z :tmi.twitch.tv PING\n",
+            "\n",
+            "2026-10-19T12:00:01.500+02:00 @room-id=1 :tmi.twitch.tv ROOMSTATE #test_channel\n",
+        )).unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!((lines[0].number, lines[0].raw.as_str()), (2, ":tmi.twitch.tv PING"));
+        assert_eq!(lines[1].at, "2026-10-19T10:00:01.500Z".parse::<DateTime<Utc>>().unwrap());
+        assert!(matches!(lines[1].message(), Ok(ServerMessage::RoomState(_))));
+
+        assert!(parse_session("PING").unwrap_err().starts_with("line 1:"));
+        assert!(parse_session("yesterday PING").unwrap_err().starts_with("line 1: invalid time"));
+    }
+
+    #[test]
+    fn test_format_line() {
+        let at = "2026-10-19T12:00:00Z".parse().unwrap();
+        let line = format_line(at, ":tmi.twitch.tv PING");
+        assert_eq!(line, "2026-10-19T12:00:00.000Z :tmi.twitch.tv PING");
+        assert_eq!(parse_session(&line).unwrap()[0].at, at);
+    }
+
+    #[test]
+    fn test_recorder() {
+        let path = std::env::temp_dir().join(format!("twitchbot2-recording-{}.irc", std::process::id()));
+        let at = "2026-10-19T12:00:00Z".parse().unwrap();
+        let ping = ServerMessage::try_from(IRCMessage::parse(":tmi.twitch.tv PING").unwrap()).unwrap();
+        let mut recorder = Recorder::open(&path).unwrap();
+        recorder.record(at, &ping).unwrap();
+        recorder.record(at + chrono::Duration::seconds(1), &ping).unwrap();
+
+        let lines = parse_session(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1].at, at + chrono::Duration::seconds(1));
+        assert!(matches!(lines[1].message(), Ok(ServerMessage::Ping(_))));
+    }
+}
@@ -0,0 +1,408 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use crate::badges::{ChatBadge, UserBadges};
+use crate::moderation::Action;
+
+/**
+ * Per-channel settings stored in `channels.settings`
+ */
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChannelSettings {
+    pub enforcement_mode: EnforcementMode,
+    pub exemptions: ExemptionPolicy,
+    pub spam_waves: WaveSettings,
+    pub flood: FloodLimits,
+    pub automod: AutomodReview,
+    pub circuit_breaker: BreakerLimit,
+    pub raid: RaidSettings,
+    pub blocked_terms: BlockedTermSync,
+}
+
+impl ChannelSettings {
+    /**
+     * Parse settings from the channel's JSON column, missing keys use defaults
+     */
+    pub fn from_json(value: &serde_json::Value) -> Result<ChannelSettings, serde_json::Error> {
+        if value.is_null() {
+            return Ok(ChannelSettings::default());
+        }
+        ChannelSettings::deserialize(value)
+    }
+}
+
+/**
+ * Whether the bot carries out its decisions in a channel
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EnforcementMode {
+    /// Messages are not moderated at all
+    Off,
+    /// Decisions are made and recorded in the audit log but never carried out
+    Shadow,
+    #[default]
+    Enforce,
+}
+
+/**
+ * Detection of similar messages posted by many first-time chatters at once
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WaveSettings {
+    pub enabled: bool,
+    /// Distinct first-time chatters that make a wave
+    pub min_users: usize,
+    pub window_secs: u32,
+    /// Estimated share of shared shingles for two messages to count as the same
+    pub similarity: f64,
+    /// Shorter messages, like greetings, are never part of a wave
+    pub min_length: usize,
+    pub action: Action,
+}
+
+impl Default for WaveSettings {
+    fn default() -> Self {
+        WaveSettings {
+            enabled: false,
+            min_users: 4,
+            window_secs: 30,
+            similarity: 0.7,
+            min_length: 12,
+            action: Action::Ban,
+        }
+    }
+}
+
+/**
+ * Review of messages held by Twitch AutoMod. Clear cases are approved or
+ * denied, mods are notified about the rest.
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutomodReview {
+    pub enabled: bool,
+    /// Deny messages matching a banned word or spam URL, and messages from known bots or banned usernames
+    pub deny_matches: bool,
+    /// Approve messages from trusted users that match nothing
+    pub approve_trusted: bool,
+    /// Approve messages from users seen before that match nothing, up to this AutoMod level
+    pub approve_seen_max_level: Option<u8>,
+    /// Chat channel notices are posted in
+    pub notice_channel: Option<String>,
+    /// Discord or Slack webhook URL notices are posted to
+    pub notice_webhook: Option<String>,
+}
+
+impl Default for AutomodReview {
+    fn default() -> Self {
+        AutomodReview {
+            enabled: false,
+            deny_matches: true,
+            approve_trusted: true,
+            approve_seen_max_level: None,
+            notice_channel: None,
+            notice_webhook: None,
+        }
+    }
+}
+
+/**
+ * Most enforcement actions the channel may see within the window before the
+ * circuit breaker holds them
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BreakerLimit {
+    pub enabled: bool,
+    pub max_actions: u32,
+    pub window_secs: u32,
+}
+
+impl Default for BreakerLimit {
+    fn default() -> Self {
+        BreakerLimit {
+            enabled: true,
+            max_actions: 30,
+            window_secs: 60,
+        }
+    }
+}
+
+/**
+ * Detection of attacks on a channel and the chat settings applied while one
+ * lasts. Each signal is off unless set.
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RaidSettings {
+    pub enabled: bool,
+    pub window_secs: u32,
+    /// Joins within the window
+    pub max_joins: Option<usize>,
+    /// Share of messages within the window that are someone's first
+    pub max_first_message_ratio: Option<f64>,
+    /// Messages the first-message ratio needs, fewer are normal chat
+    pub min_messages: usize,
+    /// Messages matching a banned word within the window
+    pub max_banned_word_hits: Option<usize>,
+    pub preset: ChatPreset,
+    pub shield_mode: bool,
+    /// How long the preset stays after the last signal
+    pub cooldown_secs: u32,
+}
+
+impl Default for RaidSettings {
+    fn default() -> Self {
+        RaidSettings {
+            enabled: false,
+            window_secs: 30,
+            max_joins: None,
+            max_first_message_ratio: None,
+            min_messages: 10,
+            max_banned_word_hits: None,
+            preset: ChatPreset::default(),
+            shield_mode: false,
+            cooldown_secs: 600,
+        }
+    }
+}
+
+/**
+ * Chat settings turned on during an attack, unset ones are left alone
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChatPreset {
+    pub emote_only: bool,
+    pub followers_only_minutes: Option<u64>,
+    pub slow_mode_secs: Option<u64>,
+    pub subscribers_only: bool,
+    pub unique_chat: bool,
+}
+
+impl Default for ChatPreset {
+    fn default() -> Self {
+        ChatPreset {
+            emote_only: false,
+            followers_only_minutes: Some(10),
+            slow_mode_secs: Some(10),
+            subscribers_only: false,
+            unique_chat: false,
+        }
+    }
+}
+
+/**
+ * Two-way sync of the channel's banned words with its Twitch blocked terms
+ */
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BlockedTermSync {
+    /// Import blocked terms as banned words of the channel
+    pub enabled: bool,
+    /// Add the channel's plain banned words as blocked terms, and remove terms whose word was deleted
+    pub push: bool,
+}
+
+/**
+ * Per-user flood and repetition limits, each one is off unless set
+ */
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FloodLimits {
+    /// Messages within the window
+    pub rate: Option<WindowLimit>,
+    /// Identical messages within the window
+    pub repeats: Option<WindowLimit>,
+    /// Characters in a message
+    pub length: Option<CountLimit>,
+    /// Share of uppercase letters
+    pub caps: Option<RatioLimit>,
+    /// Share of characters that are neither letters, digits nor whitespace
+    pub symbols: Option<RatioLimit>,
+    /// Emotes in a message
+    pub emotes: Option<CountLimit>,
+}
+
+impl FloodLimits {
+    pub fn is_enabled(&self) -> bool {
+        self != &FloodLimits::default()
+    }
+}
+
+fn default_limit_action() -> Action {
+    Action::Delete
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindowLimit {
+    pub max: usize,
+    pub window_secs: u32,
+    #[serde(default = "default_limit_action")]
+    pub action: Action,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CountLimit {
+    pub max: usize,
+    #[serde(default = "default_limit_action")]
+    pub action: Action,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RatioLimit {
+    pub max: f64,
+    /// Messages with fewer counted characters are not checked
+    #[serde(default)]
+    pub min_length: usize,
+    #[serde(default = "default_limit_action")]
+    pub action: Action,
+}
+
+/**
+ * Which chatters are never actioned by the bot
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExemptionPolicy {
+    pub broadcaster: bool,
+    pub moderators: bool,
+    pub vips: bool,
+    pub founders: bool,
+    pub artists: bool,
+    /// Subscribers with at least this many months are exempt, `None` disables
+    pub subscriber_min_months: Option<u32>,
+}
+
+impl Default for ExemptionPolicy {
+    fn default() -> Self {
+        ExemptionPolicy {
+            broadcaster: true,
+            moderators: true,
+            vips: true,
+            founders: false,
+            artists: false,
+            subscriber_min_months: None,
+        }
+    }
+}
+
+/**
+ * Reason a chatter was exempted
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Exemption {
+    Broadcaster,
+    Moderator,
+    Vip,
+    Founder,
+    Artist,
+    Subscriber,
+    Trusted,
+}
+
+impl fmt::Display for Exemption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Exemption::Broadcaster => "broadcaster",
+            Exemption::Moderator => "moderator",
+            Exemption::Vip => "vip",
+            Exemption::Founder => "founder",
+            Exemption::Artist => "artist",
+            Exemption::Subscriber => "subscriber",
+            Exemption::Trusted => "trusted",
+        };
+        f.write_str(name)
+    }
+}
+
+/**
+ * One exemption the policy checked and whether it applied
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExemptionCheck {
+    pub exemption: Exemption,
+    pub matched: bool,
+}
+
+impl ExemptionPolicy {
+    /**
+     * Evaluate every exemption enabled by the policy, in order of precedence
+     */
+    pub fn checks(&self, badges: &UserBadges, trusted: bool) -> Vec<ExemptionCheck> {
+        let subscriber = self.subscriber_min_months
+            .map(|min| badges.subscriber_months() > 0 && badges.subscriber_months() >= min);
+
+        [
+            (Exemption::Trusted, Some(trusted)),
+            (Exemption::Broadcaster, self.broadcaster.then(|| badges.is_broadcaster())),
+            (Exemption::Moderator, self.moderators.then(|| badges.is_mod())),
+            (Exemption::Vip, self.vips.then(|| badges.is_vip())),
+            (Exemption::Founder, self.founders.then(|| badges.has(&ChatBadge::Founder))),
+            (Exemption::Artist, self.artists.then(|| badges.has(&ChatBadge::Artist))),
+            (Exemption::Subscriber, subscriber),
+        ]
+        .into_iter()
+        .filter_map(|(exemption, matched)| matched.map(|matched| ExemptionCheck { exemption, matched }))
+        .collect()
+    }
+
+    /**
+     * Check the user's badges against the policy
+     */
+    pub fn check(&self, badges: &UserBadges, trusted: bool) -> Option<Exemption> {
+        self.checks(badges, trusted)
+            .into_iter()
+            .find(|check| check.matched)
+            .map(|check| check.exemption)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_policy() {
+        let policy = ExemptionPolicy::default();
+        assert_eq!(policy.check(&UserBadges::parse("moderator/1", ""), false), Some(Exemption::Moderator));
+        assert_eq!(policy.check(&UserBadges::parse("broadcaster/1", ""), false), Some(Exemption::Broadcaster));
+        assert_eq!(policy.check(&UserBadges::parse("subscriber/12", "subscriber/12"), false), None);
+        assert_eq!(policy.check(&UserBadges::default(), true), Some(Exemption::Trusted));
+        assert_eq!(policy.check(&UserBadges::default(), false), None);
+
+        let checks = policy.checks(&UserBadges::parse("vip/1", ""), false);
+        let checked: Vec<Exemption> = checks.iter().map(|check| check.exemption).collect();
+        assert_eq!(checked, vec![Exemption::Trusted, Exemption::Broadcaster, Exemption::Moderator, Exemption::Vip]);
+        assert_eq!(checks.iter().filter(|check| check.matched).count(), 1);
+    }
+
+    #[test]
+    fn test_settings_from_json() {
+        let settings = ChannelSettings::from_json(&serde_json::json!({
+            "enforcement_mode": "shadow",
+            "exemptions": { "vips": false, "founders": true, "subscriber_min_months": 6 },
+            "spam_waves": { "enabled": true, "action": { "timeout": 60 } }
+        })).unwrap();
+        assert_eq!(settings.enforcement_mode, EnforcementMode::Shadow);
+        assert_eq!(settings.spam_waves.action, Action::Timeout(60));
+        assert_eq!(settings.spam_waves.min_users, 4);
+        let policy = settings.exemptions;
+        assert!(policy.moderators);
+        assert_eq!(policy.check(&UserBadges::parse("vip/1", ""), false), None);
+        assert_eq!(policy.check(&UserBadges::parse("founder/0", "founder/2"), false), Some(Exemption::Founder));
+        assert_eq!(policy.check(&UserBadges::parse("subscriber/3", "subscriber/3"), false), None);
+        assert_eq!(policy.check(&UserBadges::parse("subscriber/6", "subscriber/7"), false), Some(Exemption::Subscriber));
+
+        assert_eq!(ChannelSettings::from_json(&serde_json::json!({})).unwrap(), ChannelSettings::default());
+        assert_eq!(ChannelSettings::default().enforcement_mode, EnforcementMode::Enforce);
+        assert!(!ChannelSettings::default().flood.is_enabled());
+
+        let settings = ChannelSettings::from_json(&serde_json::json!({ "flood": { "length": { "max": 300 } } })).unwrap();
+        assert!(settings.flood.is_enabled());
+        assert_eq!(settings.flood.length.unwrap().action, Action::Delete);
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::{HashMap, HashSet};
+use crate::moderation::{Action, DecisionTrace};
+
+/// How often chatters are swept for known bots
+pub const SWEEP_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Logins per Get Users request, the most Helix accepts
+pub const USERS_PER_REQUEST: usize = 100;
+
+/// Chatters per Get Chatters page, the most Helix accepts
+pub const CHATTERS_PER_PAGE: usize = 1000;
+
+/**
+ * A user to act on in the next sweep, the ID is looked up if not known yet
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suspect {
+    pub login: String,
+    pub user_id: Option<String>,
+    pub action: Action,
+    pub reason: String,
+    pub trace: Option<DecisionTrace>,
+}
+
+impl Suspect {
+    pub fn known_bot(login: &str, user_id: Option<String>) -> Suspect {
+        Suspect {
+            login: login.to_string(),
+            user_id,
+            action: Action::Ban,
+            reason: "Known bot".to_string(),
+            trace: None,
+        }
+    }
+}
+
+/**
+ * Suspects collected from JOINs per channel, until the next sweep
+ */
+#[derive(Debug, Default)]
+pub struct Suspects {
+    channels: HashMap<String, HashMap<String, Suspect>>,
+}
+
+impl Suspects {
+    /**
+     * Add a suspect, an earlier one with a harsher action wins
+     */
+    pub fn insert(&mut self, channel: &str, suspect: Suspect) {
+        let suspects = self.channels.entry(channel.to_string()).or_default();
+        match suspects.get(&suspect.login) {
+            Some(previous) if previous.action.harshness() >= suspect.action.harshness() => {},
+            _ => {
+                suspects.insert(suspect.login.clone(), suspect);
+            },
+        }
+    }
+
+    /**
+     * Take the channel's suspects, together with the known bots among its
+     * chatters. Chatters come with their IDs, which are filled in.
+     */
+    pub fn take(&mut self, channel: &str, chatters: &[(String, String)], known_bots: &HashSet<String>) -> Vec<Suspect> {
+        let mut suspects = self.channels.remove(channel).unwrap_or_default();
+        for (user_id, login) in chatters {
+            if let Some(suspect) = suspects.get_mut(login) {
+                suspect.user_id = Some(user_id.clone());
+            } else if known_bots.contains(login) {
+                suspects.insert(login.clone(), Suspect::known_bot(login, Some(user_id.clone())));
+            }
+        }
+
+        let mut suspects: Vec<Suspect> = suspects.into_values().collect();
+        suspects.sort_by(|a, b| a.login.cmp(&b.login));
+        suspects
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take() {
+        let mut suspects = Suspects::default();
+        suspects.insert("channel", Suspect::known_bot("bot_a", None));
+        suspects.insert("channel", Suspect { action: Action::Timeout(60), ..Suspect::known_bot("bot_a", None) });
+        suspects.insert("channel", Suspect { action: Action::Timeout(60), ..Suspect::known_bot("hoss01", None) });
+        suspects.insert("other", Suspect::known_bot("bot_b", None));
+
+        let known_bots = HashSet::from(["bot_a".to_string(), "bot_c".to_string()]);
+        let chatters = vec![
+            ("1".to_string(), "bot_a".to_string()),
+            ("2".to_string(), "viewer".to_string()),
+            ("3".to_string(), "bot_c".to_string()),
+        ];
+        let taken = suspects.take("channel", &chatters, &known_bots);
+        let taken: Vec<(&str, Option<&str>, Action)> = taken.iter()
+            .map(|suspect| (suspect.login.as_str(), suspect.user_id.as_deref(), suspect.action))
+            .collect();
+        assert_eq!(taken, vec![
+            ("bot_a", Some("1"), Action::Ban),
+            ("bot_c", Some("3"), Action::Ban),
+            ("hoss01", None, Action::Timeout(60)),
+        ]);
+
+        assert!(suspects.take("channel", &[], &known_bots).is_empty());
+        assert_eq!(suspects.take("other", &[], &known_bots).len(), 1);
+    }
+}
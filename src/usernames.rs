@@ -0,0 +1,282 @@
+use chrono::{DateTime, Duration, Utc};
+use entity::banned_username_patterns;
+use entity::sea_orm_active_enums::UsernamePatternKind;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use crate::matcher::{compile_regex, RuleError};
+use crate::moderation::Action;
+
+/// Similarity a login needs when the rule does not set one
+pub const DEFAULT_SIMILARITY: f64 = 0.8;
+
+/// How long banned logins are compared against by `recently_banned` rules
+pub const BAN_EVASION_DAYS: i64 = 7;
+
+/// Banned logins remembered per channel
+const MAX_RECENT_BANS: usize = 1000;
+
+/// Logins shorter than this, digits collapsed, are never called similar
+const MIN_SKELETON_LEN: usize = 4;
+
+/// Ordinary logins, a pattern matching all of them would action everyone
+const PROBES: [&str; 4] = ["a", "streamer", "cool_viewer_99", "x1234"];
+
+/**
+ * A banned username pattern as loaded from the database
+ */
+#[derive(Debug, Clone)]
+pub struct UsernameRule {
+    pub id: i32,
+    /// `None` for rules that apply to every channel
+    pub channel: Option<String>,
+    pub kind: UsernamePatternKind,
+    pub pattern: String,
+    pub similarity: f64,
+    pub action: Action,
+    regex: Option<Regex>,
+}
+
+impl UsernameRule {
+    /**
+     * Compile a rule, regexes are rejected if they match every login
+     */
+    pub fn new(id: i32, kind: UsernamePatternKind, pattern: &str) -> Result<UsernameRule, RuleError> {
+        let regex = match kind {
+            UsernamePatternKind::Regex => {
+                let regex = compile_regex(pattern)?;
+                if regex.is_match("") {
+                    return Err(RuleError::MatchesEmpty);
+                }
+                if PROBES.iter().all(|probe| regex.is_match(probe)) {
+                    return Err(RuleError::MatchesEverything);
+                }
+                Some(regex)
+            },
+            UsernamePatternKind::Similar if skeleton(pattern).chars().count() < MIN_SKELETON_LEN => return Err(RuleError::MatchesEmpty),
+            UsernamePatternKind::Similar | UsernamePatternKind::RecentlyBanned => None,
+        };
+
+        Ok(UsernameRule {
+            id,
+            channel: None,
+            kind,
+            pattern: pattern.to_string(),
+            similarity: DEFAULT_SIMILARITY,
+            action: Action::Ban,
+            regex,
+        })
+    }
+
+    /**
+     * Rule from its row, `channels` maps channel IDs to logins
+     */
+    pub fn from_model(model: &banned_username_patterns::Model, channels: &HashMap<i32, String>) -> Result<UsernameRule, RuleError> {
+        let timeout_duration = model.timeout_duration.and_then(|duration| u32::try_from(duration).ok());
+        Ok(UsernameRule {
+            channel: model.channel_id.and_then(|id| channels.get(&id).cloned()),
+            similarity: model.similarity.unwrap_or(DEFAULT_SIMILARITY),
+            action: Action::from_rule_action(model.action, timeout_duration),
+            ..UsernameRule::new(model.id, model.kind, &model.pattern)?
+        })
+    }
+
+    fn applies_to(&self, channel: &str) -> bool {
+        self.channel.as_deref().is_none_or(|rule_channel| rule_channel == channel)
+    }
+}
+
+/**
+ * A login that matched a username rule
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsernameMatch {
+    pub rule_id: i32,
+    pub pattern: String,
+    /// The login it resembles, for similarity rules
+    pub similar_to: Option<String>,
+    pub similarity: Option<f64>,
+    pub action: Action,
+}
+
+impl fmt::Display for UsernameMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.similar_to, self.similarity) {
+            (Some(login), Some(similarity)) => write!(f, "username pattern #{} similar to \"{}\" ({:.0}%)", self.rule_id, login, similarity * 100.0),
+            _ => write!(f, "username pattern #{} /{}/", self.rule_id, self.pattern),
+        }
+    }
+}
+
+/**
+ * Banned username patterns, and the logins recently banned per channel for
+ * catching ban evasion
+ */
+#[derive(Debug, Default)]
+pub struct UsernameMatcher {
+    rules: Vec<UsernameRule>,
+    recent_bans: HashMap<String, VecDeque<(String, DateTime<Utc>)>>,
+}
+
+impl UsernameMatcher {
+    pub fn new(rules: Vec<UsernameRule>) -> UsernameMatcher {
+        UsernameMatcher {
+            rules,
+            recent_bans: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn record_ban(&mut self, channel: &str, login: &str, at: DateTime<Utc>) {
+        let bans = self.recent_bans.entry(channel.to_string()).or_default();
+        bans.retain(|(banned, _)| banned != login);
+        bans.push_back((login.to_lowercase(), at));
+        if bans.len() > MAX_RECENT_BANS {
+            bans.pop_front();
+        }
+    }
+
+    pub fn forget_ban(&mut self, channel: &str, login: &str) {
+        if let Some(bans) = self.recent_bans.get_mut(channel) {
+            bans.retain(|(banned, _)| banned != login);
+        }
+    }
+
+    /**
+     * Check a login against the channel's rules. Returns the match with the
+     * harshest action, if any.
+     */
+    pub fn check(&self, channel: &str, login: &str, now: DateTime<Utc>) -> Option<UsernameMatch> {
+        let login = login.to_lowercase();
+        let since = now - Duration::days(BAN_EVASION_DAYS);
+
+        self.rules.iter()
+            .filter(|rule| rule.applies_to(channel))
+            .filter_map(|rule| {
+                let (similar_to, similarity) = match rule.kind {
+                    UsernamePatternKind::Regex => {
+                        return rule.regex.as_ref()
+                            .is_some_and(|regex| regex.is_match(&login))
+                            .then(|| username_match(rule, None, None));
+                    },
+                    UsernamePatternKind::Similar => (rule.pattern.clone(), similarity(&rule.pattern, &login)),
+                    UsernamePatternKind::RecentlyBanned => self.recent_bans.get(channel)?.iter()
+                        .filter(|(banned, at)| *at > since && *banned != login)
+                        .map(|(banned, _)| (banned.clone(), similarity(banned, &login)))
+                        .max_by(|a, b| a.1.total_cmp(&b.1))?,
+                };
+                (similarity >= rule.similarity).then(|| username_match(rule, Some(similar_to), Some(similarity)))
+            })
+            .max_by_key(|username_match| username_match.action.harshness())
+    }
+}
+
+fn username_match(rule: &UsernameRule, similar_to: Option<String>, similarity: Option<f64>) -> UsernameMatch {
+    UsernameMatch {
+        rule_id: rule.id,
+        pattern: rule.pattern.clone(),
+        similar_to,
+        similarity,
+        action: rule.action,
+    }
+}
+
+/**
+ * Login with runs of digits collapsed and surrounding underscores trimmed, so
+ * that numbered accounts of a farm share a skeleton
+ */
+fn skeleton(login: &str) -> String {
+    let mut skeleton = String::new();
+    for c in login.to_lowercase().trim_matches('_').chars() {
+        if c.is_ascii_digit() {
+            if !skeleton.ends_with('0') {
+                skeleton.push('0');
+            }
+        } else {
+            skeleton.push(c);
+        }
+    }
+    skeleton
+}
+
+/**
+ * Similarity of two logins from 0 to 1, one minus the edit distance of their
+ * skeletons relative to the longer one
+ */
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = skeleton(a).chars().collect();
+    let b: Vec<char> = skeleton(b).chars().collect();
+    if a.len().min(b.len()) < MIN_SKELETON_LEN {
+        return 0.0;
+    }
+
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    1.0 - previous[b.len()] as f64 / a.len().max(b.len()) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(id: i32, kind: UsernamePatternKind, pattern: &str, action: Action) -> UsernameRule {
+        UsernameRule {
+            action,
+            ..UsernameRule::new(id, kind, pattern).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_similarity() {
+        assert_eq!(similarity("hoss00312_", "hoss4_"), 1.0);
+        assert!(similarity("spammer_king", "spammer_kinq") > 0.9);
+        assert!(similarity("spammer_king", "cozy_viewer") < 0.5);
+        assert_eq!(similarity("ab1", "ab2"), 0.0);
+    }
+
+    #[test]
+    fn test_rule_validation() {
+        assert_eq!(UsernameRule::new(1, UsernamePatternKind::Regex, ".*").unwrap_err(), RuleError::MatchesEmpty);
+        assert_eq!(UsernameRule::new(1, UsernamePatternKind::Regex, "\\w").unwrap_err(), RuleError::MatchesEverything);
+        assert_eq!(UsernameRule::new(1, UsernamePatternKind::Similar, "ab12").unwrap_err(), RuleError::MatchesEmpty);
+        assert!(UsernameRule::new(1, UsernamePatternKind::Regex, "^hoss\\d+_?$").is_ok());
+    }
+
+    #[test]
+    fn test_check() {
+        let now = Utc::now();
+        let mut matcher = UsernameMatcher::new(vec![
+            rule(1, UsernamePatternKind::Regex, "^hoss\\d+_?$", Action::Timeout(600)),
+            rule(2, UsernamePatternKind::Similar, "BestViewers", Action::Ban),
+            UsernameRule { channel: Some("other".to_string()), ..rule(3, UsernamePatternKind::Regex, "^bot", Action::Ban) },
+            rule(4, UsernamePatternKind::RecentlyBanned, "", Action::Delete),
+        ]);
+
+        assert_eq!(matcher.check("channel", "Hoss00312_", now).unwrap().action, Action::Timeout(600));
+        let best = matcher.check("channel", "bestviewers2", now).unwrap();
+        assert_eq!(best.rule_id, 2);
+        assert_eq!(best.to_string(), "username pattern #2 similar to \"BestViewers\" (92%)");
+        assert!(matcher.check("channel", "botanist", now).is_none());
+        assert_eq!(matcher.check("other", "botanist", now).unwrap().rule_id, 3);
+
+        assert!(matcher.check("channel", "evil_spammer_2", now).is_none());
+        matcher.record_ban("channel", "evil_spammer", now - Duration::days(1));
+        assert_eq!(matcher.check("channel", "evil_spammer_2", now).unwrap().similar_to.as_deref(), Some("evil_spammer"));
+        assert!(matcher.check("channel", "evil_spammer", now).is_none());
+        assert!(matcher.check("channel", "evil_spammer_2", now + Duration::days(BAN_EVASION_DAYS)).is_none());
+        matcher.forget_ban("channel", "evil_spammer");
+        assert!(matcher.check("channel", "evil_spammer_2", now).is_none());
+    }
+}
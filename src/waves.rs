@@ -0,0 +1,244 @@
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, HashSet, VecDeque};
+use crate::normalize::{normalize, NormalizeOptions};
+use crate::settings::WaveSettings;
+
+/// Characters per shingle
+const SHINGLE_LEN: usize = 4;
+
+/// Hash functions in a MinHash signature
+const SIGNATURE_LEN: usize = 64;
+
+/// Messages kept per channel regardless of the window
+const MAX_WINDOW_MESSAGES: usize = 500;
+
+/**
+ * MinHash signature over the character shingles of a normalized message
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature([u64; SIGNATURE_LEN]);
+
+impl Signature {
+    pub fn new(text: &str) -> Signature {
+        let chars: Vec<char> = canonical(text).chars().collect();
+        let shingles: Vec<&[char]> = if chars.len() <= SHINGLE_LEN {
+            vec![&chars[..]]
+        } else {
+            chars.windows(SHINGLE_LEN).collect()
+        };
+
+        let mut mins = [u64::MAX; SIGNATURE_LEN];
+        for shingle in shingles {
+            let hash = fnv1a(shingle);
+            for (i, min) in mins.iter_mut().enumerate() {
+                *min = (*min).min(mix(hash ^ (i as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)));
+            }
+        }
+        Signature(mins)
+    }
+
+    /**
+     * Estimated Jaccard similarity of the two messages' shingles
+     */
+    pub fn similarity(&self, other: &Signature) -> f64 {
+        let equal = self.0.iter().zip(other.0.iter()).filter(|(a, b)| a == b).count();
+        equal as f64 / SIGNATURE_LEN as f64
+    }
+}
+
+/**
+ * Lowercased, normalized text with whitespace collapsed
+ */
+fn canonical(text: &str) -> String {
+    let text = normalize(&text.to_lowercase(), NormalizeOptions::ALL);
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn fnv1a(chars: &[char]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for c in chars {
+        for byte in (*c as u32).to_le_bytes() {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+/// splitmix64 finalizer
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/**
+ * A message from a first-time chatter, kept in the channel's window
+ */
+#[derive(Debug, Clone)]
+pub struct WaveMessage {
+    pub at: DateTime<Utc>,
+    pub user_id: String,
+    pub login: String,
+    pub message_id: String,
+    pub text: String,
+    signature: Signature,
+}
+
+impl WaveMessage {
+    pub fn new(at: DateTime<Utc>, user_id: &str, login: &str, message_id: &str, text: &str) -> WaveMessage {
+        WaveMessage {
+            at,
+            user_id: user_id.to_string(),
+            login: login.to_string(),
+            message_id: message_id.to_string(),
+            text: text.to_string(),
+            signature: Signature::new(text),
+        }
+    }
+}
+
+/**
+ * Similar messages posted by several first-time chatters
+ */
+#[derive(Debug, Clone)]
+pub struct Wave {
+    /// Text of the message that started the wave
+    pub phrase: String,
+    pub members: Vec<WaveMessage>,
+    /// `false` when the members joined a wave detected earlier
+    pub new: bool,
+}
+
+#[derive(Debug, Default)]
+struct ChannelWindow {
+    messages: VecDeque<WaveMessage>,
+    /// Waves detected within the window, later similar messages belong to them
+    waves: Vec<(Signature, DateTime<Utc>)>,
+}
+
+/**
+ * Per-channel sliding windows of first messages
+ */
+#[derive(Debug, Default)]
+pub struct WaveDetector {
+    channels: HashMap<String, ChannelWindow>,
+}
+
+impl WaveDetector {
+    /**
+     * Add a first-time chatter's message to the channel's window. Returns the
+     * wave it completes or joins, if any.
+     */
+    pub fn observe(&mut self, channel: &str, message: WaveMessage, settings: &WaveSettings) -> Option<Wave> {
+        if canonical(&message.text).chars().count() < settings.min_length {
+            return None;
+        }
+
+        let window = self.channels.entry(channel.to_string()).or_default();
+        let since = message.at - Duration::seconds(settings.window_secs.into());
+        window.messages.retain(|previous| previous.at > since);
+        window.waves.retain(|(_, at)| *at > since);
+
+        if let Some((_, at)) = window.waves.iter_mut().find(|(signature, _)| signature.similarity(&message.signature) >= settings.similarity) {
+            *at = message.at;
+            return Some(Wave {
+                phrase: message.text.clone(),
+                members: vec![message],
+                new: false,
+            });
+        }
+
+        let similar: Vec<usize> = window.messages.iter()
+            .enumerate()
+            .filter(|(_, previous)| previous.user_id != message.user_id)
+            .filter(|(_, previous)| previous.signature.similarity(&message.signature) >= settings.similarity)
+            .map(|(i, _)| i)
+            .collect();
+        let users: HashSet<&str> = similar.iter()
+            .map(|i| window.messages[*i].user_id.as_str())
+            .chain([message.user_id.as_str()])
+            .collect();
+
+        if users.len() < settings.min_users {
+            window.messages.push_back(message);
+            if window.messages.len() > MAX_WINDOW_MESSAGES {
+                window.messages.pop_front();
+            }
+            return None;
+        }
+
+        let mut members: Vec<WaveMessage> = similar.iter().rev().filter_map(|i| window.messages.remove(*i)).collect();
+        members.reverse();
+        members.push(message);
+        window.waves.push((members[0].signature.clone(), members[members.len() - 1].at));
+
+        Some(Wave {
+            phrase: members[0].text.clone(),
+            members,
+            new: true,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> WaveSettings {
+        WaveSettings {
+            enabled: true,
+            min_users: 3,
+            ..Default::default()
+        }
+    }
+
+    fn message(seconds: i64, user: &str, text: &str) -> WaveMessage {
+        let at = DateTime::from_timestamp(1_700_000_000 + seconds, 0).unwrap();
+        WaveMessage::new(at, user, user, &format!("{}-{}", user, seconds), text)
+    }
+
+    #[test]
+    fn test_similarity() {
+        let a = Signature::new("Best viewers on streamboost dot com");
+        assert_eq!(a.similarity(&Signature::new("best  VIEWERS on streamboost dot com")), 1.0);
+        assert!(a.similarity(&Signature::new("Best viewers on streamboost dot com!!")) > 0.7);
+        assert!(a.similarity(&Signature::new("what a play, that was insane")) < 0.2);
+    }
+
+    #[test]
+    fn test_wave_detection() {
+        let mut detector = WaveDetector::default();
+        let settings = settings();
+
+        assert!(detector.observe("channel", message(0, "bot1", "Get cheap viewers at streamboost"), &settings).is_none());
+        assert!(detector.observe("channel", message(1, "human", "hello everyone, first time here"), &settings).is_none());
+        assert!(detector.observe("channel", message(2, "bot1", "Get cheap viewers at streamboost"), &settings).is_none());
+        assert!(detector.observe("other", message(3, "bot2", "Get cheap viewers at streamboost"), &settings).is_none());
+
+        let wave = detector.observe("channel", message(4, "bot2", "Get cheap v1ewers at streamboost!"), &settings);
+        assert!(wave.is_none());
+        let wave = detector.observe("channel", message(5, "bot3", "get cheap viewers at streamboost"), &settings).unwrap();
+        assert!(wave.new);
+        assert_eq!(wave.phrase, "Get cheap viewers at streamboost");
+        let users: Vec<&str> = wave.members.iter().map(|member| member.login.as_str()).collect();
+        assert_eq!(users, vec!["bot1", "bot1", "bot2", "bot3"]);
+
+        // Late arrivals join the detected wave
+        let wave = detector.observe("channel", message(20, "bot4", "Get cheap viewers at streamboost"), &settings).unwrap();
+        assert!(!wave.new);
+        assert_eq!(wave.members.len(), 1);
+        assert!(detector.observe("channel", message(21, "human2", "hello everyone, first time here"), &settings).is_none());
+    }
+
+    #[test]
+    fn test_window_expiry() {
+        let mut detector = WaveDetector::default();
+        let settings = settings();
+
+        assert!(detector.observe("channel", message(0, "bot1", "Get cheap viewers at streamboost"), &settings).is_none());
+        assert!(detector.observe("channel", message(10, "bot2", "Get cheap viewers at streamboost"), &settings).is_none());
+        assert!(detector.observe("channel", message(45, "bot3", "Get cheap viewers at streamboost"), &settings).is_none());
+        assert!(detector.observe("channel", message(46, "bot4", "hi"), &settings).is_none());
+    }
+}
@@ -0,0 +1,303 @@
+use axum::body::{to_bytes, Body, Bytes};
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::Router;
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc::Sender;
+use tracing::{debug, info, warn};
+use crate::bot::BotEvent;
+use crate::eventsub;
+
+/// Oldest message accepted, Twitch retries for a shorter time than this
+pub const MAX_MESSAGE_AGE_MINUTES: i64 = 10;
+
+/// Largest request body read, notifications are a few kilobytes
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// Secret lengths Twitch accepts for webhook subscriptions
+pub const SECRET_LENGTH: std::ops::RangeInclusive<usize> = 10..=100;
+
+/**
+ * Why a message was turned away
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rejection {
+    /// Already accepted once, Twitch is retrying or someone is replaying it
+    Duplicate,
+    /// Timestamp too far from now
+    Stale,
+}
+
+/**
+ * Message IDs accepted within the allowed message age, for rejecting replays
+ */
+#[derive(Debug, Default)]
+pub struct ReplayGuard {
+    seen: HashMap<String, DateTime<Utc>>,
+}
+
+impl ReplayGuard {
+    /**
+     * Accept a message unless it is stale or its ID was accepted before
+     */
+    pub fn check(&mut self, message_id: &str, timestamp: DateTime<Utc>, now: DateTime<Utc>) -> Result<(), Rejection> {
+        let max_age = Duration::minutes(MAX_MESSAGE_AGE_MINUTES);
+        if (now - timestamp).abs() > max_age {
+            return Err(Rejection::Stale);
+        }
+
+        // IDs older than the window can go, their messages would be stale anyway
+        self.seen.retain(|_, at| now - *at <= max_age);
+        if self.seen.contains_key(message_id) {
+            return Err(Rejection::Duplicate);
+        }
+        self.seen.insert(message_id.to_string(), timestamp);
+        Ok(())
+    }
+
+    /**
+     * Accept a message ID again, e.g. after its message could not be delivered
+     */
+    pub fn forget(&mut self, message_id: &str) {
+        self.seen.remove(message_id);
+    }
+}
+
+#[derive(Clone)]
+struct WebhookState {
+    secret: String,
+    events: Sender<BotEvent>,
+    replays: Arc<Mutex<ReplayGuard>>,
+}
+
+#[derive(Deserialize)]
+struct Callback {
+    #[serde(default)]
+    challenge: Option<String>,
+    subscription: Subscription,
+    #[serde(default)]
+    event: Value,
+}
+
+#[derive(Deserialize)]
+struct Subscription {
+    #[serde(rename = "type")]
+    subscription_type: String,
+    #[serde(default)]
+    status: String,
+}
+
+/**
+ * Serve the EventSub webhook callback until the listener fails. Every path
+ * is accepted, so the callback URL can carry whatever path a proxy needs.
+ */
+pub async fn serve(listener: TcpListener, secret: String, events: Sender<BotEvent>) -> std::io::Result<()> {
+    info!("EventSub webhook listening on {}", listener.local_addr()?);
+
+    let state = WebhookState {
+        secret,
+        events,
+        replays: Arc::new(Mutex::new(ReplayGuard::default())),
+    };
+    let app = Router::new()
+        .fallback(post(callback))
+        .with_state(state);
+
+    axum::serve(listener, app).await
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+/**
+ * Whether the signature header has the shape of a SHA-256 HMAC. `verify_payload`
+ * panics on signatures of any other length.
+ */
+fn is_signature(headers: &HeaderMap) -> bool {
+    header(headers, "Twitch-Eventsub-Message-Signature")
+        .and_then(|signature| signature.strip_prefix("sha256="))
+        .is_some_and(|hex| hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/**
+ * Verify a message from Twitch and answer it: challenges are echoed back,
+ * notifications go to the bot and revocations are logged
+ */
+async fn callback(State(state): State<WebhookState>, request: Request) -> Response {
+    let (parts, body) = request.into_parts();
+    let Ok(body) = to_bytes(body, MAX_BODY_BYTES).await else {
+        return StatusCode::PAYLOAD_TOO_LARGE.into_response();
+    };
+    let request = axum::http::Request::from_parts(parts, body);
+    if !is_signature(request.headers()) || !twitch_api::eventsub::Event::verify_payload(&request, state.secret.as_bytes()) {
+        warn!("Rejected EventSub webhook message with an invalid signature");
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    let headers = request.headers();
+    let (Some(message_id), Some(timestamp), Some(message_type)) = (
+        header(headers, "Twitch-Eventsub-Message-Id"),
+        header(headers, "Twitch-Eventsub-Message-Timestamp").and_then(|timestamp| DateTime::parse_from_rfc3339(timestamp).ok()),
+        header(headers, "Twitch-Eventsub-Message-Type"),
+    ) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+
+    let checked = state.replays.lock().unwrap().check(message_id, timestamp.to_utc(), Utc::now());
+    match checked {
+        // Twitch keeps retrying until it gets a success, so duplicates are acknowledged
+        Err(Rejection::Duplicate) => {
+            debug!("Dropping EventSub webhook message {} received twice", message_id);
+            return StatusCode::NO_CONTENT.into_response();
+        },
+        Err(Rejection::Stale) => {
+            warn!("Rejected EventSub webhook message {} from {}", message_id, timestamp);
+            return StatusCode::FORBIDDEN.into_response();
+        },
+        Ok(()) => {},
+    }
+
+    // Twitch retries until it gets a success, so only a delivered message counts as seen
+    let response = deliver(&state, message_id, message_type, request.body()).await;
+    if !response.status().is_success() {
+        state.replays.lock().unwrap().forget(message_id);
+    }
+    response
+}
+
+/**
+ * Answer a verified webhook message, passing notifications on to the bot
+ */
+async fn deliver(state: &WebhookState, message_id: &str, message_type: &str, body: &[u8]) -> Response {
+    let callback: Callback = match serde_json::from_slice(body) {
+        Ok(callback) => callback,
+        Err(e) => {
+            warn!("Invalid EventSub webhook message {}: {}", message_id, e);
+            return StatusCode::BAD_REQUEST.into_response();
+        },
+    };
+    let subscription_type = callback.subscription.subscription_type;
+
+    match message_type {
+        "webhook_callback_verification" => {
+            info!("Confirmed EventSub webhook subscription to {}", subscription_type);
+            let challenge = callback.challenge.unwrap_or_default();
+            ([(axum::http::header::CONTENT_TYPE, "text/plain")], Body::from(Bytes::from(challenge))).into_response()
+        },
+        "notification" => match eventsub::parse_notification(&subscription_type, callback.event) {
+            Ok(Some(event)) => match state.events.send(event).await {
+                Ok(()) => StatusCode::NO_CONTENT.into_response(),
+                Err(_) => StatusCode::SERVICE_UNAVAILABLE.into_response(),
+            },
+            Ok(None) => StatusCode::NO_CONTENT.into_response(),
+            Err(e) => {
+                warn!("Invalid {} event in EventSub webhook message {}: {}", subscription_type, message_id, e);
+                StatusCode::BAD_REQUEST.into_response()
+            },
+        },
+        "revocation" => {
+            warn!("EventSub revoked the {} subscription: {}", subscription_type, callback.subscription.status);
+            StatusCode::NO_CONTENT.into_response()
+        },
+        _ => StatusCode::BAD_REQUEST.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hmac::{Hmac, Mac};
+    use serde_json::json;
+    use sha2::Sha256;
+    use tokio::sync::mpsc;
+
+    const SECRET: &str = "webhook-secret";
+
+    fn signature(message_id: &str, timestamp: &str, body: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(SECRET.as_bytes()).unwrap();
+        mac.update(format!("{}{}{}", message_id, timestamp, body).as_bytes());
+        let hex: String = mac.finalize().into_bytes().iter().map(|byte| format!("{:02x}", byte)).collect();
+        format!("sha256={}", hex)
+    }
+
+    #[test]
+    fn test_replay_guard() {
+        let now = Utc::now();
+        let mut guard = ReplayGuard::default();
+        assert_eq!(guard.check("a", now, now), Ok(()));
+        assert_eq!(guard.check("a", now, now + Duration::minutes(1)), Err(Rejection::Duplicate));
+        assert_eq!(guard.check("b", now - Duration::minutes(11), now), Err(Rejection::Stale));
+        assert_eq!(guard.check("c", now + Duration::minutes(11), now), Err(Rejection::Stale));
+        assert_eq!(guard.check("d", now + Duration::minutes(10), now + Duration::minutes(11)), Ok(()));
+        assert!(!guard.seen.contains_key("a"));
+
+        guard.forget("d");
+        assert_eq!(guard.check("d", now + Duration::minutes(10), now + Duration::minutes(11)), Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_callback() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (events, mut receiver) = mpsc::channel(10);
+        tokio::spawn(serve(listener, SECRET.to_string(), events));
+
+        let client = reqwest::Client::new();
+        let post = |message_id: &str, message_type: &str, body: &Value, signature: String| {
+            let timestamp = Utc::now().to_rfc3339();
+            let signature = if signature.is_empty() { self::signature(message_id, &timestamp, &body.to_string()) } else { signature };
+            client.post(format!("http://{}/eventsub", addr))
+                .header("Twitch-Eventsub-Message-Id", message_id)
+                .header("Twitch-Eventsub-Message-Timestamp", timestamp)
+                .header("Twitch-Eventsub-Message-Type", message_type)
+                .header("Twitch-Eventsub-Message-Signature", signature)
+                .body(body.to_string())
+                .send()
+        };
+
+        let verification = json!({
+            "challenge": "pogchamp-kappa-360noscope-vohiyo",
+            "subscription": { "id": "sub-1", "type": "channel.unban", "version": "1", "status": "webhook_callback_verification_pending" },
+        });
+        let response = post("message-1", "webhook_callback_verification", &verification, String::new()).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(response.text().await.unwrap(), "pogchamp-kappa-360noscope-vohiyo");
+
+        let notification = json!({
+            "subscription": { "id": "sub-1", "type": "channel.unban", "version": "1", "status": "enabled" },
+            "event": {
+                "user_id": "123", "user_login": "viewer", "user_name": "Viewer",
+                "broadcaster_user_id": "1", "broadcaster_user_login": "test_channel", "broadcaster_user_name": "Test_Channel",
+                "moderator_user_id": "2", "moderator_user_login": "a_mod", "moderator_user_name": "A_Mod",
+            },
+        });
+        let forged = post("message-2", "notification", &notification, "sha256=00".to_string()).await.unwrap();
+        assert_eq!(forged.status(), reqwest::StatusCode::FORBIDDEN);
+
+        let response = post("message-2", "notification", &notification, String::new()).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NO_CONTENT);
+        let replayed = post("message-2", "notification", &notification, String::new()).await.unwrap();
+        assert_eq!(replayed.status(), reqwest::StatusCode::NO_CONTENT);
+
+        let Some(BotEvent::Unban(unban)) = receiver.recv().await else {
+            panic!("expected an unban");
+        };
+        assert_eq!(unban.user_login, "viewer");
+        assert!(receiver.try_recv().is_err());
+
+        // An undelivered message is not taken for a duplicate when Twitch sends it again
+        receiver.close();
+        let failed = post("message-3", "notification", &notification, String::new()).await.unwrap();
+        assert_eq!(failed.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+        let retried = post("message-3", "notification", &notification, String::new()).await.unwrap();
+        assert_eq!(retried.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+    }
+}